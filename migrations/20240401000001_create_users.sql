@@ -0,0 +1,14 @@
+-- Ids are ObjectId hex strings so every backend hands out the same ids.
+-- Emails compare case-sensitively, as in MongoDB, and names have no length
+-- limit.
+CREATE TABLE users (
+    id CHAR(24) NOT NULL PRIMARY KEY,
+    email VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL,
+    first_name TEXT NOT NULL,
+    last_name TEXT NOT NULL,
+    password VARCHAR(255) NOT NULL,
+    created_at DATETIME(3) NOT NULL,
+    last_up_vote DATETIME(3) NOT NULL,
+    token_generation BIGINT NOT NULL DEFAULT 0,
+    UNIQUE KEY users_email (email)
+);
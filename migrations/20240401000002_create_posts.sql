@@ -0,0 +1,23 @@
+CREATE TABLE posts (
+    id CHAR(24) NOT NULL PRIMARY KEY,
+    user_id CHAR(24) NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    first_name TEXT NOT NULL,
+    created_at DATETIME(3) NOT NULL,
+    updated_at DATETIME(3) NULL,
+    KEY posts_created_at_id (created_at DESC, id DESC),
+    KEY posts_user_id_created_at_id (user_id, created_at DESC, id DESC),
+    FULLTEXT KEY posts_title_content (title, content),
+    CONSTRAINT posts_user_fk FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE TABLE post_revisions (
+    seq BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    post_id CHAR(24) NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    edited_at DATETIME(3) NOT NULL,
+    KEY post_revisions_post_id (post_id),
+    CONSTRAINT post_revisions_post_fk FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE
+);
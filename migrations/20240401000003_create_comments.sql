@@ -0,0 +1,18 @@
+-- `seq` keeps comments in insertion order. `parent_id` has no foreign key:
+-- replies outlive a deleted parent and are then shown at the top level.
+CREATE TABLE comments (
+    seq BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    id CHAR(36) NOT NULL,
+    post_id CHAR(24) NOT NULL,
+    user_id CHAR(24) NOT NULL,
+    parent_id CHAR(36) NULL,
+    first_name TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at DATETIME(3) NOT NULL,
+    edited_at DATETIME(3) NULL,
+    UNIQUE KEY comments_id (id),
+    KEY comments_post_id (post_id),
+    KEY comments_user_id (user_id),
+    CONSTRAINT comments_post_fk FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE,
+    CONSTRAINT comments_user_fk FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
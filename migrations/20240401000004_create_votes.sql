@@ -0,0 +1,10 @@
+CREATE TABLE votes (
+    seq BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    post_id CHAR(24) NOT NULL,
+    user_id CHAR(24) NOT NULL,
+    created_at DATETIME(3) NOT NULL,
+    UNIQUE KEY votes_post_id_user_id (post_id, user_id),
+    KEY votes_user_id (user_id),
+    CONSTRAINT votes_post_fk FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE,
+    CONSTRAINT votes_user_fk FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
@@ -0,0 +1,21 @@
+CREATE TABLE refresh_tokens (
+    seq BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    user_id CHAR(24) NOT NULL,
+    family CHAR(36) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at DATETIME(3) NOT NULL,
+    expires_at DATETIME(3) NOT NULL,
+    UNIQUE KEY refresh_tokens_token_hash (token_hash),
+    KEY refresh_tokens_family (family),
+    KEY refresh_tokens_user_id (user_id),
+    KEY refresh_tokens_expires_at (expires_at),
+    CONSTRAINT refresh_tokens_user_fk FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE TABLE revoked_tokens (
+    jti CHAR(36) NOT NULL PRIMARY KEY,
+    expires_at DATETIME(3) NOT NULL,
+    KEY revoked_tokens_expires_at (expires_at)
+);
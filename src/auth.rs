@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use mongodb::bson::oid::ObjectId;
+
+use crate::{error::MyError, model::Claims, AppState};
+
+/// Authenticated caller, resolved from the `Authorization: Bearer <jwt>`
+/// header. Handlers taking an `AuthUser` reject the request with 401 before
+/// running when the token is missing or invalid.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: ObjectId,
+    pub claims: Claims,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AuthUser {
+    type Rejection = MyError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let jwt = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim_start_matches("Bearer "))
+            .ok_or(MyError::JwtNotFoundError())?;
+
+        let (id, claims) = app_state.db.authenticate(jwt).await?;
+        Ok(AuthUser { id, claims })
+    }
+}
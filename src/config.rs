@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use axum::http::HeaderName;
+use serde::Deserialize;
+
+use crate::cors::OriginPattern;
+use crate::db::JwtKeys;
+
+type Result<T> = std::result::Result<T, ConfigError>;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:8080";
+const DEFAULT_CORS_ORIGIN: &str = "http://localhost:3000";
+
+/// Every problem found while loading the configuration, reported together
+/// so they can all be fixed in one go.
+#[derive(thiserror::Error, Debug)]
+#[error("invalid configuration:\n  - {}", .0.join("\n  - "))]
+pub struct ConfigError(pub Vec<String>);
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind_address: SocketAddr,
+    pub cors: CorsConfig,
+    pub jwt: JwtConfig,
+    pub database: DatabaseConfig,
+    pub limits: LimitsConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    pub origins: Vec<OriginPattern>,
+    pub exposed_headers: Vec<HeaderName>,
+    pub max_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    pub keys: JwtKeys,
+    pub expiry_hours: u64,
+    pub refresh_token_ttl_days: i64,
+}
+
+impl JwtConfig {
+    /// Signs with `keys` and uses the default lifetimes.
+    pub fn with_keys(keys: JwtKeys) -> Self {
+        JwtConfig {
+            keys,
+            expiry_hours: 21,
+            refresh_token_ttl_days: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    MongoDb,
+    MySql,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "mongodb" => Ok(Backend::MongoDb),
+            "mysql" => Ok(Backend::MySql),
+            _ => Err("expected mongodb or mysql".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub backend: BackendConfig,
+    pub url: String,
+    pub max_connections: u32,
+}
+
+/// The selected backend along with the settings only it needs.
+#[derive(Debug, Clone)]
+pub enum BackendConfig {
+    MongoDb(MongoConfig),
+    MySql,
+}
+
+/// Settings only the MongoDB backend needs.
+#[derive(Debug, Clone)]
+pub struct MongoConfig {
+    pub database: String,
+    pub user_collection: String,
+    pub post_collection: String,
+    pub refresh_token_collection: String,
+    pub revoked_token_collection: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LimitsConfig {
+    pub default_page_limit: usize,
+    pub max_page_limit: usize,
+    pub upvote_cooldown_secs: i64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            default_page_limit: 20,
+            max_page_limit: 100,
+            upvote_cooldown_secs: 60,
+        }
+    }
+}
+
+/// Shape of the optional TOML file. Every key can be overridden by the
+/// environment variable named next to it.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    bind_address: Option<SocketAddr>, // BIND_ADDRESS
+    #[serde(default)]
+    cors: FileCors,
+    #[serde(default)]
+    jwt: FileJwt,
+    #[serde(default)]
+    database: FileDatabase,
+    #[serde(default)]
+    limits: FileLimits,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileCors {
+    origins: Option<Vec<String>>, // CORS_ORIGINS, comma separated
+    exposed_headers: Option<Vec<String>>, // CORS_EXPOSED_HEADERS, comma separated
+    max_age_secs: Option<u64>, // CORS_MAX_AGE_SECS
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileJwt {
+    keys: Option<String>, // JWT_KEYS
+    secret: Option<String>, // JWT_SECRET
+    expiry_hours: Option<u64>, // JWT_EXPIRY_HOURS
+    refresh_token_ttl_days: Option<i64>, // REFRESH_TOKEN_TTL_DAYS
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileDatabase {
+    backend: Option<Backend>, // DATABASE_BACKEND
+    url: Option<String>, // DATABASE_URL
+    max_connections: Option<u32>, // DATABASE_MAX_CONNECTIONS
+    name: Option<String>, // MONGO_DB_DATABASE
+    user_collection: Option<String>, // MONGODB_USER_COLLECTION
+    post_collection: Option<String>, // MONGODB_POST_COLLECTION
+    refresh_token_collection: Option<String>, // MONGODB_REFRESH_TOKEN_COLLECTION
+    revoked_token_collection: Option<String>, // MONGODB_REVOKED_TOKEN_COLLECTION
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileLimits {
+    default_page_limit: Option<usize>, // DEFAULT_PAGE_LIMIT
+    max_page_limit: Option<usize>, // MAX_PAGE_LIMIT
+    upvote_cooldown_secs: Option<i64>, // UPVOTE_COOLDOWN_SECS
+}
+
+/// Collects errors while resolving settings, environment first, then file.
+#[derive(Default)]
+struct Loader {
+    env: HashMap<String, String>,
+    errors: Vec<String>,
+}
+
+impl Loader {
+    fn from_env() -> Self {
+        Loader {
+            env: std::env::vars().collect(),
+            errors: vec![],
+        }
+    }
+
+    fn var(&self, name: &str) -> Option<&str> {
+        self.raw(name).map(str::trim).filter(|value| !value.is_empty())
+    }
+
+    /// The variable exactly as set, for secrets where whitespace counts.
+    fn raw(&self, name: &str) -> Option<&str> {
+        self.env.get(name).map(String::as_str).filter(|value| !value.is_empty())
+    }
+
+    fn optional<T>(&mut self, env: &str, file: Option<T>) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = match self.var(env) {
+            Some(value) => value,
+            None => return file,
+        };
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let message = format!("{}: invalid value {:?}: {}", env, value, e);
+                self.errors.push(message);
+                None
+            }
+        }
+    }
+
+    fn required<T>(&mut self, env: &str, key: &str, file: Option<T>) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let errors = self.errors.len();
+        let value = self.optional(env, file);
+        if value.is_none() && self.errors.len() == errors {
+            self.errors.push(format!("{} (or `{}` in the config file) must be set", env, key));
+        }
+        value
+    }
+
+    fn list<T>(&mut self, env: &str, file: Option<Vec<String>>) -> Option<Vec<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let items: Vec<String> = match self.var(env) {
+            Some(value) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => file?,
+        };
+        let mut parsed = Vec::with_capacity(items.len());
+        for item in items {
+            match item.parse() {
+                Ok(value) => parsed.push(value),
+                Err(e) => self.errors.push(format!("{}: invalid value {:?}: {}", env, item, e)),
+            }
+        }
+        Some(parsed)
+    }
+
+    fn check(&mut self, ok: bool, message: &str) {
+        if !ok {
+            self.errors.push(message.to_string());
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the environment and, if present, the
+    /// TOML file named by `CONFIG_FILE` (default `config.toml`). Environment
+    /// variables take precedence over the file.
+    pub fn load() -> Result<Self> {
+        let mut loader = Loader::from_env();
+        let path = loader.var("CONFIG_FILE").map(str::to_string);
+        let file = match &path {
+            Some(path) => read_file(Path::new(path), &mut loader),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                read_file(Path::new(DEFAULT_CONFIG_FILE), &mut loader)
+            }
+            None => FileConfig::default(),
+        };
+        Self::resolve(file, loader)
+    }
+
+    fn resolve(file: FileConfig, mut loader: Loader) -> Result<Self> {
+        let bind_address = loader.optional("BIND_ADDRESS", file.bind_address);
+        let cors = CorsConfig {
+            origins: loader
+                .list("CORS_ORIGINS", file.cors.origins)
+                .unwrap_or_else(|| vec![OriginPattern::Exact(DEFAULT_CORS_ORIGIN.to_string())]),
+            exposed_headers: loader
+                .list("CORS_EXPOSED_HEADERS", file.cors.exposed_headers)
+                .unwrap_or_default(),
+            max_age_secs: loader.optional("CORS_MAX_AGE_SECS", file.cors.max_age_secs),
+        };
+
+        let keys = match (
+            loader.optional::<String>("JWT_KEYS", file.jwt.keys),
+            loader.raw("JWT_SECRET").map(str::to_string).or(file.jwt.secret),
+        ) {
+            (Some(keys), _) => {
+                let keys = JwtKeys::parse(&keys);
+                loader.check(keys.is_some(), "JWT_KEYS must be a list of kid:secret pairs");
+                keys
+            }
+            (None, Some(secret)) => {
+                let keys = JwtKeys::single("default", &secret);
+                loader.check(keys.is_some(), "JWT_SECRET must not be empty");
+                keys
+            }
+            (None, None) => {
+                loader.check(
+                    false,
+                    "JWT_KEYS or JWT_SECRET (or `jwt.keys` in the config file) must be set",
+                );
+                None
+            }
+        };
+        let expiry_hours = loader.optional("JWT_EXPIRY_HOURS", file.jwt.expiry_hours);
+        let refresh_token_ttl_days =
+            loader.optional("REFRESH_TOKEN_TTL_DAYS", file.jwt.refresh_token_ttl_days);
+        loader.check(expiry_hours != Some(0), "JWT_EXPIRY_HOURS must be at least 1");
+        loader.check(
+            refresh_token_ttl_days.is_none_or(|days| days > 0),
+            "REFRESH_TOKEN_TTL_DAYS must be at least 1",
+        );
+
+        let backend = loader
+            .optional("DATABASE_BACKEND", file.database.backend)
+            .unwrap_or(Backend::MongoDb);
+        let url = loader.required("DATABASE_URL", "database.url", file.database.url);
+        let max_connections =
+            loader.optional("DATABASE_MAX_CONNECTIONS", file.database.max_connections);
+        loader.check(max_connections != Some(0), "DATABASE_MAX_CONNECTIONS must be at least 1");
+        let mongo = match backend {
+            Backend::MongoDb => Some((
+                loader.required("MONGO_DB_DATABASE", "database.name", file.database.name),
+                loader.required(
+                    "MONGODB_USER_COLLECTION",
+                    "database.user_collection",
+                    file.database.user_collection,
+                ),
+                loader.required(
+                    "MONGODB_POST_COLLECTION",
+                    "database.post_collection",
+                    file.database.post_collection,
+                ),
+                loader.optional(
+                    "MONGODB_REFRESH_TOKEN_COLLECTION",
+                    file.database.refresh_token_collection,
+                ),
+                loader.optional(
+                    "MONGODB_REVOKED_TOKEN_COLLECTION",
+                    file.database.revoked_token_collection,
+                ),
+            )),
+            Backend::MySql => None,
+        };
+
+        let defaults = LimitsConfig::default();
+        let limits = LimitsConfig {
+            default_page_limit: loader
+                .optional("DEFAULT_PAGE_LIMIT", file.limits.default_page_limit)
+                .unwrap_or(defaults.default_page_limit),
+            max_page_limit: loader
+                .optional("MAX_PAGE_LIMIT", file.limits.max_page_limit)
+                .unwrap_or(defaults.max_page_limit),
+            upvote_cooldown_secs: loader
+                .optional("UPVOTE_COOLDOWN_SECS", file.limits.upvote_cooldown_secs)
+                .unwrap_or(defaults.upvote_cooldown_secs),
+        };
+        loader.check(limits.default_page_limit > 0, "DEFAULT_PAGE_LIMIT must be at least 1");
+        loader.check(
+            limits.default_page_limit <= limits.max_page_limit,
+            "DEFAULT_PAGE_LIMIT must not exceed MAX_PAGE_LIMIT",
+        );
+        loader.check(limits.upvote_cooldown_secs >= 0, "UPVOTE_COOLDOWN_SECS must not be negative");
+
+        if !loader.errors.is_empty() {
+            return Err(ConfigError(loader.errors));
+        }
+        let Some(keys) = keys else {
+            return Err(ConfigError(vec!["no JWT signing key configured".to_string()]));
+        };
+        // Every `None` left below has pushed an error above.
+        let backend = match mongo {
+            Some((database, user_collection, post_collection, refresh, revoked)) => {
+                BackendConfig::MongoDb(MongoConfig {
+                    database: database.unwrap(),
+                    user_collection: user_collection.unwrap(),
+                    post_collection: post_collection.unwrap(),
+                    refresh_token_collection: refresh
+                        .unwrap_or_else(|| "refresh_tokens".to_string()),
+                    revoked_token_collection: revoked
+                        .unwrap_or_else(|| "revoked_tokens".to_string()),
+                })
+            }
+            None => BackendConfig::MySql,
+        };
+        let jwt = JwtConfig::with_keys(keys);
+        Ok(Config {
+            bind_address: bind_address
+                .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.parse().unwrap()),
+            cors,
+            jwt: JwtConfig {
+                expiry_hours: expiry_hours.unwrap_or(jwt.expiry_hours),
+                refresh_token_ttl_days: refresh_token_ttl_days
+                    .unwrap_or(jwt.refresh_token_ttl_days),
+                ..jwt
+            },
+            database: DatabaseConfig {
+                backend,
+                url: url.unwrap(),
+                max_connections: max_connections.unwrap_or(10),
+            },
+            limits,
+        })
+    }
+}
+
+fn read_file(path: &Path, loader: &mut Loader) -> FileConfig {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| toml::from_str(&content).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(file) => file,
+        Err(e) => {
+            loader.errors.push(format!("{}: {}", path.display(), e));
+            FileConfig::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONGO_ENV: &[(&str, &str)] = &[
+        ("DATABASE_URL", "mongodb://localhost:27017"),
+        ("MONGO_DB_DATABASE", "kedubak"),
+        ("MONGODB_USER_COLLECTION", "users"),
+        ("MONGODB_POST_COLLECTION", "posts"),
+    ];
+
+    fn resolve(env: &[(&str, &str)], file: &str) -> Result<Config> {
+        let loader = Loader {
+            env: MONGO_ENV
+                .iter()
+                .chain(env)
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            errors: vec![],
+        };
+        Config::resolve(toml::from_str(file).unwrap(), loader)
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let file = r#"
+            bind_address = "127.0.0.1:9000"
+            [jwt]
+            secret = "from-file"
+            expiry_hours = 2
+            [limits]
+            max_page_limit = 50
+        "#;
+        let config = resolve(&[("BIND_ADDRESS", "0.0.0.0:1234"), ("JWT_SECRET", "from-env")], file)
+            .unwrap();
+
+        assert_eq!(config.bind_address, "0.0.0.0:1234".parse().unwrap());
+        assert_eq!(config.jwt.keys.secrets["default"], b"from-env");
+        assert_eq!(config.jwt.expiry_hours, 2);
+        assert_eq!(config.limits.max_page_limit, 50);
+        assert_eq!(config.limits.default_page_limit, 20);
+    }
+
+    #[test]
+    fn jwt_keys_win_over_secret() {
+        let config = resolve(&[("JWT_KEYS", "new:one,old:two"), ("JWT_SECRET", "ignored")], "")
+            .unwrap();
+
+        assert_eq!(config.jwt.keys.active_kid, "new");
+        assert_eq!(config.jwt.keys.secrets.len(), 2);
+    }
+
+    #[test]
+    fn secret_is_used_verbatim() {
+        for secret in ["a,b:c", ":", "k:v,", "a,,b", " padded "] {
+            let config = resolve(&[("JWT_SECRET", secret)], "").unwrap();
+            assert_eq!(config.jwt.keys.active_kid, "default");
+            assert_eq!(config.jwt.keys.secrets["default"], secret.as_bytes(), "{}", secret);
+        }
+        let file = "[jwt]\nsecret = \"x,y:z\"\n";
+        let config = resolve(&[], file).unwrap();
+        assert_eq!(config.jwt.keys.secrets["default"], b"x,y:z");
+    }
+
+    #[test]
+    fn reports_every_error_at_once() {
+        let ConfigError(errors) = resolve(&[("MAX_PAGE_LIMIT", "abc")], "").unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].starts_with("JWT_KEYS or JWT_SECRET"));
+        assert!(errors[1].starts_with("MAX_PAGE_LIMIT: invalid value"));
+
+        let ConfigError(errors) = resolve(&[], "[jwt]\nsecret = \"\"\n").unwrap_err();
+        assert_eq!(errors, vec!["JWT_SECRET must not be empty"]);
+
+        let ConfigError(errors) = resolve(&[("JWT_KEYS", "no-colon")], "").unwrap_err();
+        assert_eq!(errors, vec!["JWT_KEYS must be a list of kid:secret pairs"]);
+    }
+}
@@ -0,0 +1,105 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+    HeaderValue, Method,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::CorsConfig;
+
+/// An allowed origin, either exact (`https://app.example.com`) or matching
+/// any subdomain (`https://*.example.com`, which does not match
+/// `https://example.com` itself).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OriginPattern {
+    Exact(String),
+    Subdomains { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => allowed.eq_ignore_ascii_case(origin),
+            OriginPattern::Subdomains { scheme, suffix } => {
+                let Some((origin_scheme, host)) = origin.split_once("://") else {
+                    return false;
+                };
+                if !origin_scheme.eq_ignore_ascii_case(scheme) || host.len() <= suffix.len() {
+                    return false;
+                }
+                let (subdomain, rest) = host.split_at(host.len() - suffix.len());
+                rest.eq_ignore_ascii_case(suffix)
+                    && subdomain
+                        .split('.')
+                        .all(|label| !label.is_empty() && is_label(label))
+            }
+        }
+    }
+}
+
+fn is_label(label: &str) -> bool {
+    label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+impl FromStr for OriginPattern {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim_end_matches('/');
+        let (scheme, host) = match value.split_once("://") {
+            Some((scheme @ ("http" | "https"), host)) => (scheme, host),
+            _ => return Err("must start with http:// or https://".to_string()),
+        };
+        if host.is_empty() || host.contains('/') {
+            return Err("must be a scheme and host, without a path".to_string());
+        }
+        if HeaderValue::from_str(value).is_err() {
+            return Err("contains invalid characters".to_string());
+        }
+        match host.strip_prefix('*') {
+            None if !host.contains('*') => Ok(OriginPattern::Exact(value.to_string())),
+            Some(suffix) if suffix.starts_with('.') && suffix.len() > 1 && !suffix.contains('*') => {
+                Ok(OriginPattern::Subdomains {
+                    scheme: scheme.to_string(),
+                    suffix: suffix.to_string(),
+                })
+            }
+            _ => Err("`*` is only allowed as the first label, as in https://*.example.com".to_string()),
+        }
+    }
+}
+
+/// Builds the CORS layer for `config`. Requests from origins that match no
+/// pattern are logged and get no CORS headers, so the browser blocks them.
+pub fn layer(config: &CorsConfig) -> CorsLayer {
+    let origins = Arc::new(config.origins.clone());
+    let allow_origin = AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+        let origin = origin.to_str().unwrap_or_default();
+        let allowed = origins.iter().any(|pattern| pattern.matches(origin));
+        if !allowed {
+            println!("🚫 CORS: rejected origin {:?}", origin);
+        }
+        allowed
+    });
+
+    let mut layer = CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_credentials(true)
+        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE])
+        .expose_headers(config.exposed_headers.clone());
+    if let Some(max_age) = config.max_age_secs {
+        layer = layer.max_age(Duration::from_secs(max_age));
+    }
+    layer
+}
@@ -1,76 +1,160 @@
-use crate::error::MyError;
+use crate::config::{BackendConfig, Config, JwtConfig, LimitsConfig};
+use crate::error::{FieldError, MyError};
 use crate::response::{
-    GenericResponse, UserData, UserListResponse,
-    UserResponse, SingleUserResponse, SingleUserResponseGet,
+    UserData, UserResponse, SingleUserResponse, SingleUserResponseGet,
     SinglePostResponse, PostData, SinglePostResponseGet,
     SingleUserResponseDel, UserResponseDel,
+    SinglePostResponseDel, PostDataDel, MessageResponse,
+    SingleCommentResponse, CommentData,
+    SingleCommentResponseDel, CommentDataDel,
+    SingleTokenResponse, TokenData,
+    PostHistoryResponse, RevisionData,
+};
+use crate::store::{
+    mongo::MongoStore, mysql::MySqlStore, PostQuery, SearchQuery, Store, UserUpdate,
 };
 use crate::{
-    error::MyError::*, model::{UserModel, PostModel, Claims, Comments},
-    schema::{CreateUserSchema, UpdateUserSchema, CreatePostSchema, LoginSchema},
+    error::MyError::*,
+    model::{
+        UserModel, PostModel, Claims, Comments, PostRevision,
+        RefreshTokenModel, RevokedTokenModel,
+    },
+    schema::{
+        CreateUserSchema, UpdateUserSchema, CreatePostSchema,
+        LoginSchema, CreateCommentSchema, RefreshSchema, FilterOptions,
+        SearchOptions, UpdatePostSchema,
+    },
 };
 use chrono::prelude::*;
-use futures::StreamExt;
-use mongodb::bson::{doc, oid::ObjectId, Document};
-use mongodb::options::{
-    FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument
-};
-use mongodb::{bson, options::ClientOptions, Client, Collection, IndexModel};
-use std::str::FromStr;
+use mongodb::bson::oid::ObjectId;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use jsonwebtoken::{
-    encode, decode, Header, Algorithm,
+    encode, decode, decode_header, Header, Algorithm,
     EncodingKey, DecodingKey, Validation
 };
 use serde_json::{json, Value};
+use uuid::Uuid;
 use std::{time::{SystemTime, UNIX_EPOCH}};
 use crypto::{digest::Digest, sha3::Sha3};
-use axum::{
-    Json,
-    http::header::HeaderMap,
+use argon2::{
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
+    Argon2,
 };
 
-#[derive(Clone, Debug)]
+/// Application logic on top of a storage backend. Cheap to clone, every
+/// clone shares the same store.
+#[derive(Clone)]
 pub struct DB {
-    pub user_collection: Collection<UserModel>,
-    pub user_collection_doc: Collection<Document>,
-    pub post_collection: Collection<PostModel>,
-    pub post_collection_doc: Collection<Document>,
+    pub store: Arc<dyn Store>,
+    pub jwt: JwtConfig,
+    pub limits: LimitsConfig,
+}
+
+/// JWT signing secrets indexed by key id. Tokens are always signed with
+/// `active_kid`; every key in `secrets` is accepted when validating, so an
+/// old key can stay around until the tokens it signed have expired.
+#[derive(Clone)]
+pub struct JwtKeys {
+    pub active_kid: String,
+    pub secrets: HashMap<String, Vec<u8>>,
+}
+
+impl std::fmt::Debug for JwtKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtKeys")
+            .field("active_kid", &self.active_kid)
+            .field("kids", &self.secrets.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl JwtKeys {
+    /// A single signing key used as-is, so the secret may contain `,` or `:`.
+    pub fn single(kid: &str, secret: &str) -> Option<Self> {
+        if secret.is_empty() {
+            return None;
+        }
+        Some(Self {
+            active_kid: kid.to_string(),
+            secrets: HashMap::from([(kid.to_string(), secret.as_bytes().to_vec())]),
+        })
+    }
+
+    /// Parses a comma separated list of `kid:secret` pairs, the first one
+    /// being the signing key.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut active_kid = None;
+        let mut secrets = HashMap::new();
+
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (kid, secret) = entry.split_once(':')?;
+            if kid.is_empty() || secret.is_empty() {
+                return None;
+            }
+            active_kid.get_or_insert_with(|| kid.to_string());
+            secrets.insert(kid.to_string(), secret.as_bytes().to_vec());
+        }
+        Some(Self {
+            active_kid: active_kid?,
+            secrets,
+        })
+    }
 }
 
 type Result<T> = std::result::Result<T, MyError>;
-const SECRET_KEY: &[u8] = b"LgKp";
+
+/// Checks the shape of a non-empty email. Addresses longer than RFC 5321
+/// allows are refused here rather than by the MySQL column.
+fn email_error(email: &str) -> Option<&'static str> {
+    if email.trim().is_empty() {
+        None
+    } else if email.len() > 254 {
+        Some("is too long")
+    } else if !email.contains('@') {
+        Some("is invalid")
+    } else {
+        None
+    }
+}
+
+/// Number of items before `page` (1-based). Pages whose offset does not
+/// fit the stores' signed 64-bit skip are rejected rather than wrapped.
+fn page_offset(page: usize, limit: usize) -> Result<u64> {
+    page.checked_sub(1)
+        .and_then(|previous| previous.checked_mul(limit))
+        .and_then(|skip| i64::try_from(skip).ok())
+        .map(|skip| skip as u64)
+        .ok_or_else(|| ValidationError("page is out of range".to_string()))
+}
+
+/// The only unique user field is the email, so a duplicate on insert or
+/// update means the address is already registered.
+fn email_taken(e: MyError) -> MyError {
+    match e.is_duplicate() {
+        true => ConflictError("an account with this email already exists".to_string()),
+        false => e,
+    }
+}
 
 impl DB {
-    pub async fn init() -> Result<Self> {
-        let mongodb_uri = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set.");
-        let database_name =
-            std::env::var("MONGO_DB_DATABASE").expect("MONGO_DB_DATABASE must be set.");
-        let user_collection_name =
-            std::env::var("MONGODB_USER_COLLECTION").expect("MONGODB_USER_COLLECTION must be set.");
-        let post_collection_name =
-            std::env::var("MONGODB_POST_COLLECTION").expect("MONGODB_POST_COLLECTION must be set.");
-
-        let mut client_options = ClientOptions::parse(mongodb_uri).await?;
-        client_options.app_name = Some(database_name.to_string());
-
-        let client = Client::with_options(client_options)?;
-        let database = client.database(database_name.as_str());
-
-        let user_collection = database.collection(user_collection_name.as_str());
-        let post_collection = database.collection(post_collection_name.as_str());
-        let user_collection_doc =
-            database.collection::<Document>(user_collection_name.as_str());
-        let post_collection_doc =
-            database.collection::<Document>(post_collection_name.as_str());
-
-        println!("✅ Database connected successfully");
-
-        Ok(Self {
-            user_collection,
-            user_collection_doc,
-            post_collection,
-            post_collection_doc
-        })
+    /// Connects to the backend selected in `config.database`.
+    pub async fn init(config: &Config) -> Result<Self> {
+        let store: Arc<dyn Store> = match &config.database.backend {
+            BackendConfig::MongoDb(mongo) => {
+                Arc::new(MongoStore::init(&config.database, mongo).await?)
+            }
+            BackendConfig::MySql => Arc::new(MySqlStore::init(&config.database).await?),
+        };
+
+        Ok(Self::new(store, config.jwt.clone(), config.limits))
+    }
+
+    pub fn new(store: Arc<dyn Store>, jwt: JwtConfig, limits: LimitsConfig) -> Self {
+        Self { store, jwt, limits }
     }
 
     pub async fn login(&self, body: &LoginSchema)
@@ -79,24 +163,28 @@ impl DB {
                 return Err(InvalidIdentifiants());
             }
 
-            let user_doc = match self
-                .user_collection
-                .find_one(doc! {"email": body.email.to_string()}, None)
-                .await
-                {
-                    Ok(Some(doc)) => doc,
-                    Ok(None) => return Err(InvalidIdentifiants()),
-                    Err(e) => return Err(InvalidIdentifiants()),
-                };
-            let password = self.hash_string(body.password.to_string());
-            if password != user_doc.password {
+            let user_doc = match self.store.find_user_by_email(&body.email).await {
+                Ok(Some(doc)) => doc,
+                Ok(None) => return Err(InvalidIdentifiants()),
+                Err(_) => return Err(InvalidIdentifiants()),
+            };
+            if !self.verify_password(&body.password, &user_doc.password)? {
                 return Err(InvalidIdentifiants());
             }
-            let jwt = self.generate_token(&user_doc)?;
+            if !user_doc.password.starts_with("$argon2") {
+                // Upgrade legacy unsalted SHA3 hashes now that we know the
+                // plaintext is correct.
+                let password = self.hash_string(body.password.to_string())?;
+                self.store
+                    .replace_password(user_doc.id, &user_doc.password, &password)
+                    .await?;
+            }
+            let (jwt, refresh_token) = self.start_session(&user_doc, None).await?;
             Ok(SingleUserResponse {
                 ok: true,
                 data: UserData {
                     token: jwt,
+                    refreshToken: refresh_token,
                     user: self.doc_to_user(&user_doc)?,
                 }
             })
@@ -104,138 +192,89 @@ impl DB {
     
     pub async fn create_user(&self, body: &CreateUserSchema)
         -> Result<SingleUserResponse> {
-            if body.email.is_empty() || body.firstName.is_empty() ||
-                body.lastName.is_empty() || body.password.is_empty() {
-                    return Err(InvalidIdentifiants());
+            let mut fields = vec![];
+            for (field, value) in [
+                ("email", &body.email),
+                ("firstName", &body.firstName),
+                ("lastName", &body.lastName),
+                ("password", &body.password),
+            ] {
+                if value.trim().is_empty() {
+                    fields.push(FieldError::new(field, "must not be empty"));
                 }
-            let document = self.create_user_document(body)?;
-
-            let options = IndexOptions::builder().unique(true).build();
-            let index = IndexModel::builder()
-                .keys(doc! {"email": 1})
-                .options(options)
-                .build();
+            }
+            if let Some(message) = email_error(&body.email) {
+                fields.push(FieldError::new("email", message));
+            }
+            if !fields.is_empty() {
+                return Err(InvalidFieldsError(fields));
+            }
+            let user_doc = self.create_user_document(body)?;
+            self.store.insert_user(&user_doc).await.map_err(email_taken)?;
+            let (jwt, refresh_token) = self.start_session(&user_doc, None).await?;
 
-            match self.user_collection.create_index(index, None).await {
-                Ok(_) => {}
-                Err(e) => return Err(MongoQueryError(e)),
-            };
-            let insert_result = match self.user_collection_doc.insert_one(&document, None).await {
-                Ok(result) => result,
-                Err(e) => {
-                    if e.to_string()
-                        .contains("E11000 duplicate key error collection")
-                        {
-                            return Err(MongoDuplicateError(e));
-                        }
-                    return Err(MongoQueryError(e));
-                }
-            };
-            let new_id = insert_result
-                .inserted_id
-                .as_object_id()
-                .expect("issue with new _id");
-
-            let user_doc = match self
-                .user_collection
-                .find_one(doc! {"_id": new_id}, None)
-                .await
-                {
-                    Ok(Some(doc)) => doc,
-                    Ok(None) => return Err(NotFoundError(new_id.to_string())),
-                    Err(e) => return Err(MongoQueryError(e)),
-                };
             Ok(SingleUserResponse {
                 ok: true,
                 data: UserData {
-                    token: self.generate_token(&user_doc)?,
+                    token: jwt,
+                    refreshToken: refresh_token,
                     user: self.doc_to_user(&user_doc)?,
                 },
             })
     }
 
-    pub async fn post(&self, headers: &HeaderMap, body: &CreatePostSchema)
-        -> Result<SinglePostResponse> {
-            let authorization_header = match headers.get("Authorization") {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let header_str = match authorization_header.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(JwtNotFoundError("".to_string())),
-            };
-            let jwt = header_str.trim_start_matches("Bearer ");
-            let obj_id = match self.id_from_jwt(jwt.to_string()) {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
+    pub async fn refresh(&self, body: &RefreshSchema)
+        -> Result<SingleTokenResponse> {
+            let token_hash = self.hash_refresh_token(&body.refreshToken);
 
-            let document = self.create_post_document(body, obj_id).await?;
-
-            let insert_result = match self.post_collection_doc.insert_one(&document, None).await {
-                Ok(result) => result,
-                Err(e) => {
-                    if e.to_string()
-                        .contains("E11000 duplicate key error collection")
-                        {
-                            return Err(MongoDuplicateError(e));
-                        }
-                    return Err(MongoQueryError(e));
+            // Consume the token atomically: only one request can flip `used`,
+            // anything else presenting the same token is a replay.
+            let token_doc = match self.store.consume_refresh_token(&token_hash).await? {
+                Some(doc) => doc,
+                None => {
+                    let known = self.store.find_refresh_token(&token_hash).await?;
+                    if let Some(doc) = known {
+                        self.store.revoke_refresh_family(&doc.family).await?;
+                    }
+                    return Err(InvalidTokenError("refresh token reused or revoked".to_string()));
                 }
             };
+            if token_doc.expiresAt < Utc::now() {
+                return Err(InvalidTokenError("refresh token expired".to_string()));
+            }
 
-            let new_id = insert_result
-                .inserted_id
-                .as_object_id()
-                .expect("issue with new _id");
-
-            let post_doc = match self
-                .post_collection
-                .find_one(doc! {"_id": new_id}, None)
-                .await
-                {
-                    Ok(Some(doc)) => doc,
-                    Ok(None) => return Err(NotFoundError(new_id.to_string())),
-                    Err(e) => return Err(MongoQueryError(e)),
-                };
-            Ok(SinglePostResponse {
+            let user_doc = match self.store.find_user(token_doc.userId).await? {
+                Some(doc) => doc,
+                None => return Err(InvalidTokenError("unknown user".to_string())),
+            };
+            let (jwt, refresh_token) =
+                self.start_session(&user_doc, Some(token_doc.family)).await?;
+            Ok(SingleTokenResponse {
                 ok: true,
-                data: PostData {
-                    createdAt: post_doc.createdAt.to_string(),
-                    userId: new_id.to_string(),
-                    firstName: post_doc.firstName,
-                    title: post_doc.title,
-                    content: post_doc.content,
-                    comments: post_doc.comments,
-                    upVotes: post_doc.upVotes,
+                data: TokenData {
+                    token: jwt,
+                    refreshToken: refresh_token,
                 },
             })
     }
 
-    pub async fn connected(&self, headers: &HeaderMap)
+    pub async fn post(&self, user_id: ObjectId, body: &CreatePostSchema)
+        -> Result<SinglePostResponse> {
+            let post_doc = self.create_post_document(body, user_id).await?;
+            self.store.insert_post(&post_doc).await?;
+
+            Ok(SinglePostResponse {
+                ok: true,
+                data: self.doc_to_post(post_doc),
+            })
+    }
+
+    pub async fn connected(&self, user_id: ObjectId)
         -> Result<SingleUserResponseGet> {
-            let authorization_header = match headers.get("Authorization") {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let header_str = match authorization_header.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(JwtNotFoundError("".to_string())),
+            let user_doc = match self.store.find_user(user_id).await? {
+                Some(doc) => doc,
+                None => return Err(NotFoundError("user", user_id.to_string())),
             };
-            let jwt = header_str.trim_start_matches("Bearer ");
-            let obj_id = match self.id_from_jwt(jwt.to_string()) {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let user_doc = match self
-                .user_collection
-                .find_one(doc! {"_id": obj_id}, None)
-                .await
-                {
-                    Ok(Some(doc)) => doc,
-                    Ok(None) => return Err(NotFoundError(obj_id.to_string())),
-                    Err(e) => return Err(MongoQueryError(e)),
-                };
             Ok(SingleUserResponseGet {
                 ok: true,
                 data: UserResponse {
@@ -246,250 +285,429 @@ impl DB {
             })
     }
 
-    pub async fn get_post(&self, headers: &HeaderMap)
+    pub async fn get_post(&self, opts: &FilterOptions)
         -> Result<SinglePostResponseGet> {
-            let authorization_header = match headers.get("Authorization") {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let header_str = match authorization_header.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(JwtNotFoundError("".to_string())),
-            };
-            let jwt = header_str.trim_start_matches("Bearer ");
-            let obj_id = match self.id_from_jwt(jwt.to_string()) {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let mut cursor = self.post_collection.find(None, None).await?;
-            let mut post_list: Vec<PostData> = Vec::new();
-
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok(post) => post_list.push(PostData {
-                        createdAt: post.createdAt.to_string(),
-                        userId: post.userId,
-                        firstName: post.firstName,
-                        title: post.title,
-                        content: post.content,
-                        comments: post.comments,
-                        upVotes: post.upVotes,
-                    }),
-                    Err(e) => return Err(e.into()),
-                }
-            }
-            Ok(SinglePostResponseGet {
+            self.list_posts(None, opts).await
+    }
+
+    pub async fn get_id_post(&self, id: &str)
+        -> Result<SinglePostResponse> {
+            let post_doc = self.find_post(id).await?;
+            Ok(SinglePostResponse {
                 ok: true,
-                data: post_list,
+                data: self.doc_to_post(post_doc),
             })
     }
 
-    pub async fn get_id_post(&self, headers: &HeaderMap, id: &str)
+    pub async fn search_post(&self, opts: &SearchOptions)
         -> Result<SinglePostResponseGet> {
-            if id == "undefined" {
-                return Err(InvalidIdentifiants());
+            if opts.q.trim().is_empty() {
+                return Err(ValidationError("q must not be empty".to_string()));
             }
-            let authorization_header = match headers.get("Authorization") {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let header_str = match authorization_header.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(JwtNotFoundError("".to_string())),
-            };
-            let jwt = header_str.trim_start_matches("Bearer ");
-            let obj_id = match self.id_from_jwt(jwt.to_string()) {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let mut cursor = self
-                .post_collection
-                .find(doc !{"userId": obj_id}, None)
-                .await?;
-            let mut post_list: Vec<PostData> = Vec::new();
-
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok(post) => post_list.push(PostData {
-                        createdAt: post.createdAt.to_string(),
-                        userId: post.userId,
-                        firstName: post.firstName,
-                        title: post.title,
-                        content: post.content,
-                        comments: post.comments,
-                        upVotes: post.upVotes,
-                    }),
-                    Err(e) => return Err(e.into()),
+            let (page, limit, skip) = self.paginate(opts.page, opts.limit)?;
+            if let (Some(from), Some(to)) = (opts.from, opts.to) {
+                if from > to {
+                    return Err(ValidationError("from must be before to".to_string()));
                 }
             }
+
+            let query = SearchQuery {
+                text: opts.q.trim().to_string(),
+                author: opts.author.to_owned(),
+                from: opts.from,
+                to: opts.to,
+                skip,
+                limit: limit as i64,
+            };
+            let (total, posts) = self.store.search_posts(&query).await?;
             Ok(SinglePostResponseGet {
                 ok: true,
-                data: post_list,
+                data: posts
+                    .into_iter()
+                    .map(|(post, score)| PostData {
+                        score: Some(score),
+                        ..self.doc_to_post(post)
+                    })
+                    .collect(),
+                total: Some(total),
+                page: Some(page as u64),
+                limit: limit as i64,
+                next_cursor: None,
             })
     }
 
-    pub async fn get_user_post(&self, headers: &HeaderMap)
+    pub async fn get_user_post(&self, user_id: ObjectId, opts: &FilterOptions)
         -> Result<SinglePostResponseGet> {
-            let authorization_header = match headers.get("Authorization") {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
+            self.list_posts(Some(user_id.to_string()), opts).await
+    }
+
+    pub async fn remove(&self, user_id: ObjectId)
+        -> Result<SingleUserResponseDel> {
+            let user_doc = match self.store.find_user(user_id).await? {
+                Some(doc) => doc,
+                None => return Err(NotFoundError("user", user_id.to_string())),
             };
-            let header_str = match authorization_header.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(JwtNotFoundError("".to_string())),
+            let counts = match self.store.delete_user_cascade(user_id).await? {
+                Some(counts) => counts,
+                None => return Err(NotFoundError("user", user_id.to_string())),
             };
-            let jwt = header_str.trim_start_matches("Bearer ");
-            let obj_id = match self.id_from_jwt(jwt.to_string()) {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
+
+            Ok(SingleUserResponseDel {
+                ok: true,
+                data: UserResponseDel {
+                    email: user_doc.email,
+                    firstName: user_doc.firstName,
+                    lastName: user_doc.lastName,
+                    removed: true,
+                    postsRemoved: counts.posts,
+                    commentsRemoved: counts.comments,
+                    votesRemoved: counts.votes,
+                }
+            })
+    }
+
+    pub async fn delete_post(&self, user_id: ObjectId, id: &str)
+        -> Result<SinglePostResponseDel> {
+            let post_doc = self.find_post(id).await?;
+            if post_doc.userId != user_id.to_string() {
+                return Err(ForbiddenError("only the author can delete this post".to_string()));
+            }
+            let post_id = post_doc.id.ok_or_else(|| NotFoundError("post", id.to_string()))?;
+
+            match self.store.delete_post(post_id).await? {
+                false => Err(NotFoundError("post", id.to_string())),
+                true => Ok(SinglePostResponseDel {
+                    ok: true,
+                    data: PostDataDel {
+                        post: self.doc_to_post(post_doc),
+                        removed: true,
+                    },
+                }),
+            }
+    }
+
+    pub async fn edit_post(&self, user_id: ObjectId, id: &str, body: &UpdatePostSchema)
+        -> Result<SinglePostResponse> {
+            if body.title.is_none() && body.content.is_none() {
+                return Err(ValidationError("no field to update".to_string()));
+            }
+            if body.title.as_ref().is_some_and(|t| t.trim().is_empty())
+                || body.content.as_ref().is_some_and(|c| c.trim().is_empty()) {
+                    return Err(ValidationError("title and content must not be empty".to_string()));
+                }
+            let post_doc = self.find_post(id).await?;
+            if post_doc.userId != user_id.to_string() {
+                return Err(ForbiddenError("only the author can edit this post".to_string()));
+            }
+            let post_id = post_doc.id.ok_or_else(|| NotFoundError("post", id.to_string()))?;
+
+            let revision = PostRevision {
+                title: post_doc.title.to_owned(),
+                content: post_doc.content.to_owned(),
+                editedAt: Utc::now(),
             };
-            let mut cursor = self
-                .post_collection
-                .find(doc !{"userId": obj_id}, None)
+            // The store only applies the edit while the post still holds the
+            // values we just read, which keeps the history accurate if two
+            // edits race.
+            let updated = self
+                .store
+                .update_post(
+                    post_id,
+                    &revision,
+                    body.title.as_ref().unwrap_or(&post_doc.title),
+                    body.content.as_ref().unwrap_or(&post_doc.content),
+                )
                 .await?;
-            let mut post_list: Vec<PostData> = Vec::new();
-
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok(post) => post_list.push(PostData {
-                        createdAt: post.createdAt.to_string(),
-                        userId: post.userId,
-                        firstName: post.firstName,
-                        title: post.title,
-                        content: post.content,
-                        comments: post.comments,
-                        upVotes: post.upVotes,
-                    }),
-                    Err(e) => return Err(e.into()),
-                }
+
+            match updated {
+                Some(doc) => Ok(SinglePostResponse {
+                    ok: true,
+                    data: self.doc_to_post(doc),
+                }),
+                None => Err(ConflictError("post was modified concurrently".to_string())),
             }
-            Ok(SinglePostResponseGet {
+    }
+
+    pub async fn get_post_history(&self, id: &str)
+        -> Result<PostHistoryResponse> {
+            let post_doc = self.find_post(id).await?;
+            Ok(PostHistoryResponse {
                 ok: true,
-                data: post_list,
+                data: post_doc
+                    .history
+                    .into_iter()
+                    .map(|revision| RevisionData {
+                        title: revision.title,
+                        content: revision.content,
+                        editedAt: revision.editedAt.to_string(),
+                    })
+                    .collect(),
             })
     }
 
-    pub async fn remove(&self, headers: &HeaderMap)
-        -> Result<SingleUserResponseDel> {
-            let authorization_header = match headers.get("Authorization") {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let header_str = match authorization_header.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(JwtNotFoundError("".to_string())),
+    pub async fn vote(&self, user_id: ObjectId, id: &str)
+        -> Result<MessageResponse> {
+            let voter = user_id.to_string();
+
+            let post_doc = self.find_post(id).await?;
+            if post_doc.upVotes.contains(&voter) {
+                return Err(ConflictError("post already upvoted".to_string()));
+            }
+            let post_id = post_doc.id.ok_or_else(|| NotFoundError("post", id.to_string()))?;
+
+            // Claim the cooldown slot first so two concurrent votes from the
+            // same user cannot both get through. Stores keep milliseconds, so
+            // `now` is truncated to find the claim again when releasing it.
+            let now = Utc::now().trunc_subsecs(3);
+            let cooldown_end = now - chrono::Duration::seconds(self.limits.upvote_cooldown_secs);
+            if !self.store.claim_upvote(user_id, cooldown_end, now).await? {
+                return Err(ForbiddenError("you can only vote once per minute".to_string()));
+            }
+
+            let added = self.store.add_vote(post_id, &voter).await;
+            if !matches!(added, Ok(true)) {
+                // The vote did not count, so neither does the cooldown.
+                self.store.release_upvote(user_id, now, cooldown_end).await?;
+            }
+            match added? {
+                true => Ok(MessageResponse {
+                    ok: true,
+                    message: "post upvoted".to_string(),
+                }),
+                false => match self.store.find_post(post_id).await? {
+                    Some(_) => Err(ConflictError("post already upvoted".to_string())),
+                    None => Err(NotFoundError("post", id.to_string())),
+                },
+            }
+    }
+
+    pub async fn comment(&self, user_id: ObjectId, id: &str, body: &CreateCommentSchema)
+        -> Result<SingleCommentResponse> {
+            if body.content.trim().is_empty() {
+                return Err(ValidationError("content must not be empty".to_string()));
+            }
+            let post_id = ObjectId::parse_str(id)
+                .map_err(|_| InvalidIDError(id.to_string()))?;
+
+            let user_doc = match self.store.find_user(user_id).await? {
+                Some(doc) => doc,
+                None => return Err(NotFoundError("user", user_id.to_string())),
             };
-            let jwt = header_str.trim_start_matches("Bearer ");
-            let obj_id = match self.id_from_jwt(jwt.to_string()) {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
+            let comment = Comments {
+                id: Uuid::new_v4().to_string(),
+                userId: user_id.to_string(),
+                parentId: body.parentId.to_owned(),
+                firstName: user_doc.firstName,
+                content: body.content.to_owned(),
+                createdAt: Utc::now(),
+                editedAt: None,
             };
-            let filter = doc! {"_id": obj_id };
-
-            let user_doc = match self
-                .user_collection
-                .find_one(doc! {"_id": obj_id}, None)
-                .await
-                {
-                    Ok(Some(doc)) => doc,
-                    Ok(None) => return Err(NotFoundError(obj_id.to_string())),
-                    Err(e) => return Err(MongoQueryError(e)),
-                };
-            let result = self
-                .user_collection
-                .delete_one(filter, None)
-                .await
-                .map_err(MongoQueryError)?;
-
-            match result.deleted_count {
-                0 => Err(NotFoundError(obj_id.to_string())),
-                _ => Ok((SingleUserResponseDel {
+
+            if !self.store.push_comment(post_id, &comment).await? {
+                let post_exists = self.store.find_post(post_id).await?.is_some();
+                if post_exists && body.parentId.is_some() {
+                    return Err(BadRequestError("parent comment not found on this post".to_string()));
+                }
+                return Err(NotFoundError("post", id.to_string()));
+            }
+            Ok(SingleCommentResponse {
+                ok: true,
+                data: self.doc_to_comment(comment),
+            })
+    }
+
+    pub async fn edit_comment(
+        &self,
+        user_id: ObjectId,
+        post_id: &str,
+        comment_id: &str,
+        body: &CreateCommentSchema,
+    ) -> Result<SingleCommentResponse> {
+            if body.content.trim().is_empty() {
+                return Err(ValidationError("content must not be empty".to_string()));
+            }
+            let (obj_id, mut comment) = self.find_own_comment(user_id, post_id, comment_id).await?;
+
+            let now = Utc::now();
+            if !self.store.update_comment(obj_id, comment_id, &body.content, now).await? {
+                return Err(NotFoundError("comment", comment_id.to_string()));
+            }
+            comment.content = body.content.to_owned();
+            comment.editedAt = Some(now.into());
+            Ok(SingleCommentResponse {
+                ok: true,
+                data: self.doc_to_comment(comment),
+            })
+    }
+
+    pub async fn delete_comment(&self, user_id: ObjectId, post_id: &str, comment_id: &str)
+        -> Result<SingleCommentResponseDel> {
+            let (obj_id, comment) = self.find_own_comment(user_id, post_id, comment_id).await?;
+
+            match self.store.remove_comment(obj_id, comment_id).await? {
+                false => Err(NotFoundError("comment", comment_id.to_string())),
+                true => Ok(SingleCommentResponseDel {
                     ok: true,
-                    data: UserResponseDel {
-                        email: user_doc.email,
-                        firstName: user_doc.firstName,
-                        lastName: user_doc.lastName,
+                    data: CommentDataDel {
+                        comment: self.doc_to_comment(comment),
                         removed: true,
-                    }
-                })),
+                    },
+                }),
             }
     }
 
-    pub async fn edit(&self, headers: &HeaderMap, body: &CreateUserSchema)
+    pub async fn edit(&self, user_id: ObjectId, body: &UpdateUserSchema)
         -> Result<SingleUserResponseGet> {
-            let authorization_header = match headers.get("Authorization") {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
-            };
-            let header_str = match authorization_header.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(JwtNotFoundError("".to_string())),
-            };
-            let jwt = header_str.trim_start_matches("Bearer ");
-            let obj_id = match self.id_from_jwt(jwt.to_string()) {
-                Some(value) => value,
-                None => return Err(JwtNotFoundError("".to_string())),
+            let mut fields = vec![];
+            for (field, value) in [
+                ("email", &body.email),
+                ("firstName", &body.firstName),
+                ("lastName", &body.lastName),
+                ("password", &body.password),
+            ] {
+                if value.as_ref().is_some_and(|v| v.trim().is_empty()) {
+                    fields.push(FieldError::new(field, "must not be empty"));
+                }
+            }
+            if let Some(message) = body.email.as_deref().and_then(email_error) {
+                fields.push(FieldError::new("email", message));
+            }
+            if !fields.is_empty() {
+                return Err(InvalidFieldsError(fields));
+            }
+            let mut update = UserUpdate {
+                email: body.email.to_owned(),
+                first_name: body.firstName.to_owned(),
+                last_name: body.lastName.to_owned(),
+                password: None,
             };
+            if update.is_empty() && body.password.is_none() {
+                return Err(ValidationError("no field to update".to_string()));
+            }
 
-            let new_user = CreateUserSchema {
-                email: body.email.to_string(),
-                password: self.hash_string(body.password.to_string()),
-                firstName: body.firstName.to_string(),
-                lastName: body.lastName.to_string(),
-            };
-            let update = doc! {
-                "$set": bson::to_document(&new_user).map_err(MongoSerializeBsonError)?,
-            };
+            if body.email.is_some() || body.password.is_some() {
+                let current_password = match &body.currentPassword {
+                    Some(value) => value,
+                    None => return Err(ValidationError(
+                        "currentPassword is required to change email or password".to_string()
+                    )),
+                };
+                let user_doc = match self.store.find_user(user_id).await? {
+                    Some(doc) => doc,
+                    None => return Err(NotFoundError("user", user_id.to_string())),
+                };
+                if !self.verify_password(current_password, &user_doc.password)? {
+                    return Err(InvalidIdentifiants());
+                }
+            }
+            if let Some(password) = &body.password {
+                update.password = Some(self.hash_string(password.to_string())?);
+            }
 
-            let options = FindOneAndUpdateOptions::builder()
-                .return_document(ReturnDocument::After)
-                .build();
-            if let Some(doc) = self
-                .user_collection
-                    .find_one_and_update(doc! {"_id": obj_id}, update, options)
-                    .await
-                    .map_err(MongoQueryError)?
-                    {
-                        let user = self.doc_to_user(&doc)?;
-                        let user_response = SingleUserResponseGet {
-                            ok: true,
-                            data: UserResponse {
-                                email: user.email,
-                                firstName: user.firstName,
-                                lastName: user.lastName,
-                            },
-                        };
-                        Ok(user_response)
-                    } else {
-                        Err(NotFoundError(obj_id.to_string()))
-                    }
+            let updated = self.store.update_user(user_id, &update).await.map_err(email_taken)?;
+            let user_doc = match updated {
+                Some(doc) => doc,
+                None => return Err(NotFoundError("user", user_id.to_string())),
+            };
+            if body.firstName.is_some() {
+                self.store
+                    .rename_author(&user_id.to_string(), &user_doc.firstName)
+                    .await?;
+            }
+            Ok(SingleUserResponseGet {
+                ok: true,
+                data: self.doc_to_user(&user_doc)?,
+            })
     }
 
-    fn id_from_jwt(&self, jwt: String) -> Option<ObjectId> {
-        let key = jsonwebtoken::DecodingKey::from_secret(SECRET_KEY);
+    /// Ends the session the access token belongs to: the token itself and
+    /// the refresh chain named by its `sid`. Other devices stay logged in.
+    pub async fn logout(&self, user_id: ObjectId, claims: &Claims)
+        -> Result<MessageResponse> {
+            let expires_at = Utc
+                .timestamp_opt(claims.exp as i64, 0)
+                .single()
+                .unwrap_or_else(Utc::now);
+            let revoked = RevokedTokenModel {
+                jti: claims.jti.to_string(),
+                expiresAt: expires_at,
+            };
+            self.store.revoke_token(&revoked).await?;
+            // The refresh chain would otherwise hand out a fresh access token
+            // right after logging out. Tokens issued before `sid` existed do
+            // not say which chain is theirs, so all of them go.
+            match claims.sid.is_empty() {
+                false => self.store.revoke_refresh_family(&claims.sid).await?,
+                true => self.store.revoke_user_refresh_tokens(user_id).await?,
+            }
+            Ok(MessageResponse {
+                ok: true,
+                message: "logged out".to_string(),
+            })
+    }
 
-        let token_message = jsonwebtoken::decode::<Claims>(&jwt,
-            &key, &jsonwebtoken::Validation::new(Algorithm::HS384));
+    pub async fn logout_all(&self, user_id: ObjectId)
+        -> Result<MessageResponse> {
+            if !self.store.bump_token_generation(user_id).await? {
+                return Err(NotFoundError("user", user_id.to_string()));
+            }
+            self.store.revoke_user_refresh_tokens(user_id).await?;
+            Ok(MessageResponse {
+                ok: true,
+                message: "logged out everywhere".to_string(),
+            })
+    }
 
-        if token_message.is_err() {
-            return Option::None
+    /// Validates `jwt` and checks it against the revocation list and the
+    /// owner's current token generation.
+    pub async fn authenticate(&self, jwt: &str) -> Result<(ObjectId, Claims)> {
+        let claims = match self.decode_jwt(jwt) {
+            Some(value) => value,
+            None => return Err(JwtNotFoundError()),
+        };
+        let user_id = match self.id_from_claims(&claims) {
+            Some(value) => value,
+            None => return Err(JwtNotFoundError()),
+        };
+        // Revocation is keyed on the jti, so a token without one could
+        // never be revoked on its own.
+        if claims.jti.is_empty() {
+            return Err(InvalidTokenError("token has no id".to_string()));
+        }
+        if self.store.is_token_revoked(&claims.jti).await? {
+            return Err(InvalidTokenError("token revoked".to_string()));
         }
-        let id = token_message.unwrap().claims.name;
-        let json_value: Value = serde_json::from_str(&id).unwrap();
-        let true_id = json_value["$oid"].as_str().unwrap();
-        let obj_id = match ObjectId::parse_str(true_id) {
-            Ok(ObjectId) => ObjectId,
-            Err(_) => return Option::None,
+        let user_doc = match self.store.find_user(user_id).await? {
+            Some(doc) => doc,
+            None => return Err(InvalidTokenError("unknown user".to_string())),
         };
-        Option::Some(obj_id)
+        if user_doc.tokenGeneration != claims.gen {
+            return Err(InvalidTokenError("token revoked".to_string()));
+        }
+        Ok((user_id, claims))
     }
 
-    fn is_valid_jwt(&self, jwt: String) -> bool {
-        let obj_id = match self.id_from_jwt(jwt.to_string()) {
-            Some(value) => value,
-            None => return false,
+    fn decode_jwt(&self, jwt: &str) -> Option<Claims> {
+        let header = decode_header(jwt).ok()?;
+        let validation = Validation::new(Algorithm::HS384);
+
+        // Tokens carry the id of the key that signed them; without one we
+        // have to try every key that is still active.
+        let secrets: Vec<&Vec<u8>> = match header.kid {
+            Some(kid) => vec![self.jwt.keys.secrets.get(&kid)?],
+            None => self.jwt.keys.secrets.values().collect(),
         };
-        return true;
+        secrets.into_iter().find_map(|secret| {
+            decode::<Claims>(jwt, &DecodingKey::from_secret(secret), &validation)
+                .ok()
+                .map(|data| data.claims)
+        })
+    }
+
+    fn id_from_claims(&self, claims: &Claims) -> Option<ObjectId> {
+        let json_value: Value = serde_json::from_str(&claims.name).ok()?;
+        let true_id = json_value["$oid"].as_str()?;
+        ObjectId::parse_str(true_id).ok()
     }
 
     fn doc_to_user(&self, user: &UserModel) -> Result<UserResponse> {
@@ -502,86 +720,308 @@ impl DB {
         Ok(user_response)
     }
 
-    fn create_user_document(
-        &self,
-        body: &CreateUserSchema,
-        ) -> Result<bson::Document> {
-        let user = CreateUserSchema {
-            email: body.email.clone(),
-            password: self.hash_string(body.password.clone()),
-            firstName: body.firstName.clone(),
-            lastName: body.lastName.clone(),
+    async fn find_post(&self, id: &str) -> Result<PostModel> {
+        let post_id = ObjectId::parse_str(id)
+            .map_err(|_| InvalidIDError(id.to_string()))?;
+
+        match self.store.find_post(post_id).await? {
+            Some(doc) => Ok(doc),
+            None => Err(NotFoundError("post", id.to_string())),
+        }
+    }
+
+    /// Validates the `page` and `limit` query parameters and returns them
+    /// with defaults applied, along with the number of items to skip.
+    fn paginate(&self, page: Option<usize>, limit: Option<usize>)
+        -> Result<(usize, usize, u64)> {
+        let page = page.unwrap_or(1);
+        let limit = limit
+            .unwrap_or(self.limits.default_page_limit)
+            .min(self.limits.max_page_limit);
+        if page == 0 || limit == 0 {
+            return Err(ValidationError("page and limit must be at least 1".to_string()));
+        }
+        Ok((page, limit, page_offset(page, limit)?))
+    }
+
+    /// Returns one page of the posts written by `author`, or of all posts,
+    /// newest first.
+    async fn list_posts(&self, author: Option<String>, opts: &FilterOptions)
+        -> Result<SinglePostResponseGet> {
+        let (page, limit, skip) = self.paginate(opts.page, opts.limit)?;
+        if let Some(cursor) = &opts.cursor {
+            return self.list_posts_after(author, cursor, limit).await;
+        }
+
+        let total = self.store.count_posts(author.as_deref()).await?;
+        let posts = self
+            .store
+            .list_posts(&PostQuery {
+                author,
+                after: None,
+                skip,
+                limit: limit as i64,
+            })
+            .await?;
+        Ok(SinglePostResponseGet {
+            ok: true,
+            data: posts.into_iter().map(|post| self.doc_to_post(post)).collect(),
+            total: Some(total),
+            page: Some(page as u64),
+            limit: limit as i64,
+            next_cursor: None,
+        })
+    }
+
+    /// Keyset variant of `list_posts`: returns the posts strictly older than
+    /// `cursor` in `(createdAt, _id)` order. An empty cursor starts from the
+    /// newest post.
+    async fn list_posts_after(&self, author: Option<String>, cursor: &str, limit: usize)
+        -> Result<SinglePostResponseGet> {
+        let after = if cursor.is_empty() {
+            None
+        } else {
+            match self.decode_cursor(cursor) {
+                Some(value) => Some(value),
+                None => return Err(ValidationError("cursor is invalid".to_string())),
+            }
+        };
+        // Fetch one extra post to know whether another page exists.
+        let mut posts = self
+            .store
+            .list_posts(&PostQuery {
+                author,
+                after,
+                skip: 0,
+                limit: (limit + 1) as i64,
+            })
+            .await?;
+        let next_cursor = if posts.len() > limit {
+            posts.truncate(limit);
+            posts.last().and_then(|post| self.encode_cursor(post))
+        } else {
+            None
         };
-        let serialized_data = bson::to_bson(&user).map_err(MongoSerializeBsonError)?;
-        let document = serialized_data.as_document().unwrap();
+        Ok(SinglePostResponseGet {
+            ok: true,
+            data: posts.into_iter().map(|post| self.doc_to_post(post)).collect(),
+            total: None,
+            page: None,
+            limit: limit as i64,
+            next_cursor,
+        })
+    }
 
-        let datetime = Utc::now();
+    fn encode_cursor(&self, post: &PostModel) -> Option<String> {
+        let id = post.id?;
+        Some(format!("{:016x}{}", post.createdAt.timestamp_millis(), id.to_hex()))
+    }
+
+    fn decode_cursor(&self, cursor: &str) -> Option<(DateTime<Utc>, ObjectId)> {
+        if cursor.len() != 40 || !cursor.is_ascii() {
+            return None;
+        }
+        let millis = i64::from_str_radix(&cursor[..16], 16).ok()?;
+        let id = ObjectId::parse_str(&cursor[16..]).ok()?;
+        Some((Utc.timestamp_millis_opt(millis).single()?, id))
+    }
+
+    fn doc_to_comment(&self, comment: Comments) -> CommentData {
+        CommentData {
+            id: comment.id,
+            userId: comment.userId,
+            parentId: comment.parentId,
+            depth: None,
+            firstName: comment.firstName,
+            content: comment.content,
+            createdAt: comment.createdAt.to_string(),
+            editedAt: comment.editedAt.map(|date| date.to_chrono().to_string()),
+        }
+    }
+
+    /// Orders comments depth-first so every reply directly follows its
+    /// parent, and sets each one's `depth`. Replies whose parent was deleted
+    /// are shown as top-level comments.
+    fn thread_comments(&self, comments: Vec<Comments>) -> Vec<CommentData> {
+        let ids: HashSet<String> = comments.iter().map(|c| c.id.to_owned()).collect();
+        let mut children: HashMap<Option<String>, Vec<Comments>> = HashMap::new();
+        for comment in comments {
+            let parent = comment.parentId.to_owned().filter(|parent| ids.contains(parent));
+            children.entry(parent).or_default().push(comment);
+        }
+
+        let mut threaded = Vec::new();
+        let mut stack: Vec<(Comments, usize)> = children
+            .remove(&None)
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .map(|comment| (comment, 0))
+            .collect();
+        while let Some((comment, depth)) = stack.pop() {
+            if let Some(replies) = children.remove(&Some(comment.id.to_owned())) {
+                stack.extend(replies.into_iter().rev().map(|reply| (reply, depth + 1)));
+            }
+            threaded.push(CommentData {
+                depth: Some(depth),
+                ..self.doc_to_comment(comment)
+            });
+        }
+        threaded
+    }
 
-        let mut doc_with_dates = doc! {
-            "createdAt": datetime,
-            "lastUpVote": datetime,
+    /// Loads the post and the comment `comment_id` on it, checking that
+    /// `user_id` wrote either of them.
+    async fn find_own_comment(&self, user_id: ObjectId, post_id: &str, comment_id: &str)
+        -> Result<(ObjectId, Comments)> {
+        let post_doc = self.find_post(post_id).await?;
+        let obj_id = post_doc.id.ok_or_else(|| NotFoundError("post", post_id.to_string()))?;
+        let caller = user_id.to_string();
+        let comment = match post_doc.comments.into_iter().find(|c| c.id == comment_id) {
+            Some(comment) => comment,
+            None => return Err(NotFoundError("comment", comment_id.to_string())),
         };
-        doc_with_dates.extend(document.clone());
+        if comment.userId != caller && post_doc.userId != caller {
+            return Err(ForbiddenError(
+                "only the author of the comment or the post can change it".to_string()
+            ));
+        }
+        Ok((obj_id, comment))
+    }
 
-        Ok(doc_with_dates)
+    fn doc_to_post(&self, post: PostModel) -> PostData {
+        PostData {
+            id: post.id.map(|id| id.to_hex()).unwrap_or_default(),
+            createdAt: post.createdAt.to_string(),
+            userId: post.userId,
+            firstName: post.firstName,
+            title: post.title,
+            content: post.content,
+            comments: self.thread_comments(post.comments),
+            upVotes: post.upVotes,
+            updatedAt: post.updatedAt.map(|date| date.to_chrono().to_string()),
+            score: None,
+        }
+    }
+
+    fn create_user_document(
+        &self,
+        body: &CreateUserSchema,
+        ) -> Result<UserModel> {
+        let datetime = Utc::now();
+
+        Ok(UserModel {
+            id: ObjectId::new(),
+            email: body.email.to_owned(),
+            firstName: body.firstName.to_owned(),
+            lastName: body.lastName.to_owned(),
+            password: self.hash_string(body.password.clone())?,
+            createdAt: datetime,
+            lastUpVote: datetime - chrono::Duration::seconds(self.limits.upvote_cooldown_secs),
+            tokenGeneration: 0,
+        })
     }
 
     async fn create_post_document(
         &self,
         body: &CreatePostSchema,
         obj_id: ObjectId,
-        ) -> Result<bson::Document> {
-        let user_doc = match self
-            .user_collection
-            .find_one(doc! {"_id": obj_id}, None)
-            .await
-            {
-                Ok(Some(doc)) => doc,
-                Ok(None) => return Err(NotFoundError(obj_id.to_string())),
-                Err(e) => return Err(MongoQueryError(e)),
-            };
-        let datetime = Utc::now();
-        let post = PostModel {
-            userId: obj_id.to_string().to_owned(),
+        ) -> Result<PostModel> {
+        let user_doc = match self.store.find_user(obj_id).await? {
+            Some(doc) => doc,
+            None => return Err(NotFoundError("user", obj_id.to_string())),
+        };
+        Ok(PostModel {
+            id: Some(ObjectId::new()),
+            userId: obj_id.to_string(),
             title: body.title.to_owned(),
             content: body.content.to_owned(),
             firstName: user_doc.firstName,
             comments: vec![],
             upVotes: vec![],
-            createdAt: datetime,
-        };
-        let serialized_data = bson::to_bson(&post).map_err(MongoSerializeBsonError)?;
-        let document = serialized_data.as_document().unwrap();
-        Ok(document.clone())
+            createdAt: Utc::now(),
+            updatedAt: None,
+            history: vec![],
+        })
     }
 
-    fn generate_token(&self, user: &UserModel)
+    /// Issues an access token and a refresh token for the same session.
+    /// Passing `family` rotates an existing session instead of starting one.
+    async fn start_session(&self, user: &UserModel, family: Option<String>)
+        -> Result<(String, String)> {
+        let family = family.unwrap_or_else(|| Uuid::new_v4().to_string());
+        let jwt = self.generate_token(user, &family)?;
+        let refresh_token = self.issue_refresh_token(user.id, family).await?;
+        Ok((jwt, refresh_token))
+    }
+
+    fn generate_token(&self, user: &UserModel, sid: &str)
         -> Result<String> {
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        let header = jsonwebtoken::Header::new(Algorithm::HS384);
+        let mut header = Header::new(Algorithm::HS384);
+        header.kid = Some(self.jwt.keys.active_kid.to_string());
         let claims = Claims {
             name: json!(user.id).to_string(),
-            exp: now + (21 * 3600),
+            exp: now + (self.jwt.expiry_hours * 3600),
             iat: now,
+            jti: Uuid::new_v4().to_string(),
+            gen: user.tokenGeneration,
+            sid: sid.to_string(),
         };
+        let secret = &self.jwt.keys.secrets[&self.jwt.keys.active_kid];
 
         Ok(encode(&header, &claims,
-        &EncodingKey::from_secret(SECRET_KEY))?)
-    }
-    fn verify_token(&self, jwt: &str)
-        -> bool {
-        let jwt_split = jwt.split('.');
-        let jwt_split:Vec<_> = jwt_split.collect();
-
-        if jwt_split.len() == 3 {
-            let message = jwt_split[0].to_owned() + "." + jwt_split[1];
-            let signature = jwt_split[2];
-            let key = jsonwebtoken::DecodingKey::from_secret(SECRET_KEY);
-            return jsonwebtoken::crypto::verify(signature, message.as_bytes(), &key, Algorithm::HS384).unwrap_or(false)
+        &EncodingKey::from_secret(secret))?)
+    }
+
+    /// Stores a new refresh token of `family` for `user_id` and returns its
+    /// plaintext.
+    async fn issue_refresh_token(&self, user_id: ObjectId, family: String)
+        -> Result<String> {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let now = Utc::now();
+        let refresh = RefreshTokenModel {
+            id: None,
+            userId: user_id,
+            family,
+            tokenHash: self.hash_refresh_token(&token),
+            used: false,
+            revoked: false,
+            createdAt: now,
+            expiresAt: now + chrono::Duration::days(self.jwt.refresh_token_ttl_days),
+        };
+        self.store.insert_refresh_token(&refresh).await?;
+        Ok(token)
+    }
+
+    fn hash_refresh_token(&self, token: &str) -> String {
+        // Refresh tokens are 256 random bits, so a fast unsalted digest is
+        // enough to keep them unusable if the collection leaks.
+        let mut hasher = Sha3::sha3_256();
+        hasher.input(token.as_bytes());
+        hasher.result_str()
+    }
+
+    fn hash_string(&self, hash_str: String) -> Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default().hash_password(hash_str.as_bytes(), &salt)?;
+        Ok(hash.to_string())
+    }
+
+    fn verify_password(&self, password: &str, stored: &str) -> Result<bool> {
+        if !stored.starts_with("$argon2") {
+            return Ok(self.legacy_hash_string(password) == stored);
         }
-        false
+        let parsed = PasswordHash::new(stored)?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok())
     }
-    fn hash_string(&self, hash_str: String) -> String {
+
+    fn legacy_hash_string(&self, hash_str: &str) -> String {
         let mut hasher = Sha3::sha3_384();
         hasher.input(hash_str.as_bytes());
         hasher.result_str()
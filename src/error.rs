@@ -1,126 +1,163 @@
-use axum::{http::StatusCode, Json};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::Serialize;
 
 #[derive(thiserror::Error, Debug)]
 pub enum MyError {
     #[error("MongoDB error")]
-    MongoError(#[from] mongodb::error::Error),
+    MongoError(Box<mongodb::error::Error>),
     #[error("duplicate key error: {0}")]
-    MongoErrorKind(mongodb::error::ErrorKind),
+    MongoErrorKind(Box<mongodb::error::ErrorKind>),
     #[error("duplicate key error: {0}")]
-    MongoDuplicateError(mongodb::error::Error),
+    MongoDuplicateError(Box<mongodb::error::Error>),
+    #[error("duplicate key error: {0}")]
+    DuplicateError(String),
     #[error("error during mongodb query: {0}")]
-    MongoQueryError(mongodb::error::Error),
+    MongoQueryError(Box<mongodb::error::Error>),
     #[error("error serializing BSON")]
     MongoSerializeBsonError(#[from] mongodb::bson::ser::Error),
+    #[error("error deserializing BSON")]
+    MongoDeserializeBsonError(#[from] mongodb::bson::de::Error),
     #[error("validation error")]
     MongoDataError(#[from] mongodb::bson::document::ValueAccessError),
+    #[error("unsupported MongoDB deployment: {0}")]
+    UnsupportedDeploymentError(String),
+    #[error("conflicting MongoDB index: {0}")]
+    IndexMismatchError(String),
+    #[error("MySQL error: {0}")]
+    SqlError(#[from] sqlx::Error),
+    #[error("MySQL migration error: {0}")]
+    SqlMigrateError(#[from] sqlx::migrate::MigrateError),
     #[error("invalid ID: {0}")]
     InvalidIDError(String),
-    #[error("Bad Identifiants")]
+    #[error("{0}")]
+    BadRequestError(String),
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("some fields are invalid")]
+    InvalidFieldsError(Vec<FieldError>),
+    #[error("invalid email or password")]
     InvalidIdentifiants(),
-    #[error("User with ID: {0} not found")]
-    NotFoundError(String),
-    #[error("Jwt not found: {0}")]
-    JwtNotFoundError(String),
-    #[error("Jwt")]
+    #[error("{0} {1} not found")]
+    NotFoundError(&'static str, String),
+    #[error("{0}")]
+    ForbiddenError(String),
+    #[error("{0}")]
+    ConflictError(String),
+    #[error("missing bearer token")]
+    JwtNotFoundError(),
+    #[error("invalid token: {0}")]
+    InvalidTokenError(String),
+    #[error("failed to sign token: {0}")]
     JwtError(#[from] jsonwebtoken::errors::Error),
+    #[error("password hashing error: {0}")]
+    PasswordHashError(#[from] argon2::password_hash::Error),
+}
+
+/// A single invalid field of a request body, listed in `details`.
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
 }
 
+/// Body of every error response.
 #[derive(Serialize)]
 struct ErrorResponse {
-    status: &'static str,
+    ok: bool,
+    code: &'static str,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Vec<FieldError>>,
 }
 
-impl Into<(axum::http::StatusCode, Json<serde_json::Value>)> for MyError {
-    fn into(self) -> (axum::http::StatusCode, Json<serde_json::Value>) {
-        let (status, error_response) = match self {
-            MyError::MongoErrorKind(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ErrorResponse {
-                    status: "error",
-                    message: format!("MongoDB error kind: {}", e),
-                },
-            ),
-            MyError::MongoDuplicateError(_) => (
-                StatusCode::CONFLICT,
-                ErrorResponse {
-                    status: "fail",
-                    message: "Note with that title already exists".to_string(),
-                },
-            ),
-            MyError::InvalidIDError(id) => (
-                StatusCode::BAD_REQUEST,
-                ErrorResponse {
-                    status: "fail",
-                    message: format!("invalid ID: {}", id),
-                },
-            ),
-            MyError::InvalidIdentifiants() => (
-                StatusCode::UNAUTHORIZED,
-                ErrorResponse {
-                    status: "fail",
-                    message: format!("Bad Identifiants"),
-                },
-            ),
-            MyError::NotFoundError(id) => (
-                StatusCode::NOT_FOUND,
-                ErrorResponse {
-                    status: "fail",
-                    message: format!("User with ID: {} not found", id),
-                },
-            ),
-            MyError::MongoError(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ErrorResponse {
-                    status: "error",
-                    message: format!("MongoDB error: {}", e),
-                },
-            ),
-            MyError::MongoQueryError(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ErrorResponse {
-                    status: "error",
-                    message: format!("MongoDB error: {}", e),
-                },
-            ),
-            MyError::MongoSerializeBsonError(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ErrorResponse {
-                    status: "error",
-                    message: format!("MongoDB error: {}", e),
-                },
-            ),
-            MyError::JwtError(e) => (
-                StatusCode::UNAUTHORIZED,
-                ErrorResponse {
-                    status: "error",
-                    message: format!("Jwt error: {}", e),
-                },
-            ),
-            MyError::JwtNotFoundError(id) => (
-                StatusCode::UNAUTHORIZED,
-                ErrorResponse {
-                    status: "fail",
-                    message: format!("Jwt not found: {}", id),
-                },
-            ),
-            MyError::MongoDataError(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ErrorResponse {
-                    status: "error",
-                    message: format!("MongoDB error: {}", e),
-                },
-            ),
-        };
-        (status, Json(serde_json::to_value(error_response).unwrap()))
+impl From<mongodb::error::Error> for MyError {
+    fn from(e: mongodb::error::Error) -> Self {
+        MyError::MongoError(Box::new(e))
     }
 }
 
-impl From<MyError> for (StatusCode, ErrorResponse) {
-    fn from(err: MyError) -> (StatusCode, ErrorResponse) {
-        err.into()
+impl MyError {
+    /// Wraps the error of a failed MongoDB operation.
+    pub fn query(e: mongodb::error::Error) -> Self {
+        MyError::MongoQueryError(Box::new(e))
+    }
+
+    pub fn is_duplicate(&self) -> bool {
+        matches!(self, MyError::MongoDuplicateError(_) | MyError::DuplicateError(_))
+    }
+
+    /// HTTP status and stable machine readable code of the error.
+    pub fn status_code(&self) -> (StatusCode, &'static str) {
+        match self {
+            MyError::MongoDuplicateError(_) | MyError::DuplicateError(_) => {
+                (StatusCode::CONFLICT, "duplicate")
+            }
+            MyError::ConflictError(_) => (StatusCode::CONFLICT, "conflict"),
+            MyError::InvalidIDError(_) => (StatusCode::BAD_REQUEST, "invalid_id"),
+            MyError::BadRequestError(_) => (StatusCode::BAD_REQUEST, "bad_request"),
+            MyError::ValidationError(_) | MyError::InvalidFieldsError(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "validation_failed")
+            }
+            MyError::InvalidIdentifiants() => (StatusCode::UNAUTHORIZED, "invalid_credentials"),
+            MyError::JwtNotFoundError() => (StatusCode::UNAUTHORIZED, "missing_token"),
+            MyError::InvalidTokenError(_) => (StatusCode::UNAUTHORIZED, "invalid_token"),
+            MyError::NotFoundError(..) => (StatusCode::NOT_FOUND, "not_found"),
+            MyError::ForbiddenError(_) => (StatusCode::FORBIDDEN, "forbidden"),
+            MyError::MongoError(_)
+            | MyError::MongoErrorKind(_)
+            | MyError::MongoQueryError(_)
+            | MyError::MongoSerializeBsonError(_)
+            | MyError::MongoDeserializeBsonError(_)
+            | MyError::MongoDataError(_)
+            | MyError::UnsupportedDeploymentError(_)
+            | MyError::IndexMismatchError(_)
+            | MyError::SqlError(_)
+            | MyError::SqlMigrateError(_)
+            | MyError::JwtError(_)
+            | MyError::PasswordHashError(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal_error")
+            }
+        }
     }
 }
 
+impl IntoResponse for MyError {
+    fn into_response(self) -> Response {
+        let (status, code) = self.status_code();
+        let message = match &self {
+            MyError::MongoDuplicateError(_) | MyError::DuplicateError(_) => {
+                "resource already exists".to_string()
+            }
+            // Storage, hashing and signing failures are logged but not exposed.
+            _ if status == StatusCode::INTERNAL_SERVER_ERROR => {
+                eprintln!("🔥 {}: {:?}", self, self);
+                "internal server error".to_string()
+            }
+            _ => self.to_string(),
+        };
+        let details = match self {
+            MyError::InvalidFieldsError(fields) => Some(fields),
+            _ => None,
+        };
+        let body = ErrorResponse {
+            ok: false,
+            code,
+            message,
+            details,
+        };
+        (status, Json(body)).into_response()
+    }
+}
@@ -1,157 +1,196 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, Query, State},
-    http::header::HeaderMap,
+    extract::State,
     http::StatusCode,
     response::IntoResponse,
-    response::Html,
-    Json,
 };
 
 use crate::{
+    auth::AuthUser,
     error::MyError,
+    extract::{Json, Path, Query},
     schema::{CreateUserSchema, LoginSchema,
-    CreatePostSchema, FilterOptions, UpdateUserSchema},
+    CreatePostSchema, FilterOptions, UpdateUserSchema,
+    CreateCommentSchema, RefreshSchema, SearchOptions,
+    UpdatePostSchema},
     AppState,
 };
 
 pub async fn register_handler(
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<CreateUserSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .create_user(&body)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.create_user(&body).await?;
+    Ok((StatusCode::CREATED, Json(res)))
 }
 
 pub async fn login_handler(
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<LoginSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .login(&body)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.login(&body).await?;
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+pub async fn refresh_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<RefreshSchema>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.refresh(&body).await?;
+    Ok(Json(res))
+}
+
+pub async fn logout_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.logout(auth.id, &auth.claims).await?;
+    Ok(Json(res))
+}
+
+pub async fn logout_all_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.logout_all(auth.id).await?;
+    Ok(Json(res))
 }
 
 pub async fn edit_user_handler(
-    headers: HeaderMap,
+    auth: AuthUser,
     State(app_state): State<Arc<AppState>>,
-    Json(body): Json<CreateUserSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .edit(&headers, &body)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok(Json(res)),
-        Err(e) => Err(e.into()),
-    }
+    Json(body): Json<UpdateUserSchema>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.edit(auth.id, &body).await?;
+    Ok(Json(res))
 }
 
 pub async fn delete_user_handler(
-    headers: HeaderMap,
+    auth: AuthUser,
     State(app_state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .remove(&headers)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok(Json(res)),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.remove(auth.id).await?;
+    Ok(Json(res))
 }
 
 pub async fn connected_handler(
-    headers: HeaderMap,
+    auth: AuthUser,
     State(app_state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .connected(&headers)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok(Json(res)),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.connected(auth.id).await?;
+    Ok(Json(res))
 }
 
 pub async fn get_post_handler(
-    headers: HeaderMap,
+    _auth: AuthUser,
+    Query(opts): Query<FilterOptions>,
     State(app_state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .get_post(&headers)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok(Json(res)),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.get_post(&opts).await?;
+    Ok(Json(res))
+}
+
+pub async fn search_post_handler(
+    _auth: AuthUser,
+    Query(opts): Query<SearchOptions>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.search_post(&opts).await?;
+    Ok(Json(res))
 }
 
 pub async fn get_user_post_handler(
-    headers: HeaderMap,
+    auth: AuthUser,
+    Query(opts): Query<FilterOptions>,
     State(app_state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .get_user_post(&headers)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok(Json(res)),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.get_user_post(auth.id, &opts).await?;
+    Ok(Json(res))
 }
 
 pub async fn get_id_post_handler(
     Path(id): Path<String>,
-    headers: HeaderMap,
+    _auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.get_id_post(&id).await?;
+    Ok(Json(res))
+}
+
+pub async fn delete_post_handler(
+    Path(id): Path<String>,
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.delete_post(auth.id, &id).await?;
+    Ok(Json(res))
+}
+
+pub async fn edit_post_handler(
+    Path(id): Path<String>,
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdatePostSchema>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.edit_post(auth.id, &id, &body).await?;
+    Ok(Json(res))
+}
+
+pub async fn get_post_history_handler(
+    Path(id): Path<String>,
+    _auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.get_post_history(&id).await?;
+    Ok(Json(res))
+}
+
+pub async fn vote_handler(
+    Path(id): Path<String>,
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.vote(auth.id, &id).await?;
+    Ok(Json(res))
+}
+
+pub async fn comment_handler(
+    Path(id): Path<String>,
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateCommentSchema>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.comment(auth.id, &id, &body).await?;
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+pub async fn edit_comment_handler(
+    Path((id, comment_id)): Path<(String, String)>,
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateCommentSchema>,
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.edit_comment(auth.id, &id, &comment_id, &body).await?;
+    Ok(Json(res))
+}
+
+pub async fn delete_comment_handler(
+    Path((id, comment_id)): Path<(String, String)>,
+    auth: AuthUser,
     State(app_state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .get_id_post(&headers, &id)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok(Json(res)),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.delete_comment(auth.id, &id, &comment_id).await?;
+    Ok(Json(res))
 }
 
 pub async fn post_handler(
-    headers: HeaderMap,
+    auth: AuthUser,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<CreatePostSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state
-        .db
-        .post(&headers, &body)
-        .await
-        .map_err(MyError::from)
-    {
-        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
-        Err(e) => Err(e.into()),
-    }
+) -> Result<impl IntoResponse, MyError> {
+    let res = app_state.db.post(auth.id, &body).await?;
+    Ok((StatusCode::CREATED, Json(res)))
 }
 
 pub async fn health_checker_handler() -> impl IntoResponse {
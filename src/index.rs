@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, Database, IndexModel};
+
+use crate::error::MyError;
+
+type Result<T> = std::result::Result<T, MyError>;
+
+const NAMESPACE_NOT_FOUND: i32 = 26;
+
+/// One index the application relies on. Names follow MongoDB's default
+/// `field_direction` convention so indexes created before the registry
+/// existed are recognised instead of clashing.
+pub struct IndexSpec {
+    pub collection: String,
+    pub name: &'static str,
+    pub keys: Document,
+    pub options: IndexOptions,
+}
+
+impl IndexSpec {
+    fn new(collection: &str, name: &'static str, keys: Document) -> Self {
+        IndexSpec {
+            collection: collection.to_string(),
+            name,
+            keys,
+            options: IndexOptions::default(),
+        }
+    }
+
+    fn unique(mut self) -> Self {
+        self.options.unique = Some(true);
+        self
+    }
+
+    fn expire_at(mut self) -> Self {
+        self.options.expire_after = Some(Duration::from_secs(0));
+        self
+    }
+
+    /// Whether `index`, as listed by the server, is the index this spec
+    /// describes.
+    fn matches(&self, index: &IndexModel) -> bool {
+        let options = index.options.clone().unwrap_or_default();
+        self.keys_match(index)
+            && options.unique.unwrap_or(false) == self.options.unique.unwrap_or(false)
+            && options.expire_after == self.options.expire_after
+    }
+
+    fn keys_match(&self, index: &IndexModel) -> bool {
+        let text_fields: Vec<&str> = self
+            .keys
+            .iter()
+            .filter(|(_, value)| value.as_str() == Some("text"))
+            .map(|(field, _)| field.as_str())
+            .collect();
+        if text_fields.is_empty() {
+            return self.keys.len() == index.keys.len()
+                && self
+                    .keys
+                    .iter()
+                    .zip(index.keys.iter())
+                    .all(|((a, x), (b, y))| a == b && same_direction(x, y));
+        }
+        // The server lists a text index under `_fts`/`_ftsx` and keeps the
+        // indexed fields in its weights.
+        let weights = index.options.as_ref().and_then(|options| options.weights.as_ref());
+        index.keys.contains_key("_fts")
+            && weights.is_some_and(|weights| {
+                weights.len() == text_fields.len()
+                    && text_fields.iter().all(|field| weights.contains_key(field))
+            })
+    }
+}
+
+/// Directions may come back as any numeric type (the shell writes doubles).
+fn same_direction(a: &Bson, b: &Bson) -> bool {
+    fn number(value: &Bson) -> Option<f64> {
+        match value {
+            Bson::Int32(n) => Some(*n as f64),
+            Bson::Int64(n) => Some(*n as f64),
+            Bson::Double(n) => Some(*n),
+            _ => None,
+        }
+    }
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+pub struct CollectionNames<'a> {
+    pub users: &'a str,
+    pub posts: &'a str,
+    pub refresh_tokens: &'a str,
+    pub revoked_tokens: &'a str,
+}
+
+pub fn registry(names: &CollectionNames) -> Vec<IndexSpec> {
+    vec![
+        IndexSpec::new(names.users, "email_1", doc! {"email": 1}).unique(),
+        IndexSpec::new(names.posts, "createdAt_-1__id_-1", doc! {"createdAt": -1, "_id": -1}),
+        IndexSpec::new(
+            names.posts,
+            "userId_1_createdAt_-1__id_-1",
+            doc! {"userId": 1, "createdAt": -1, "_id": -1},
+        ),
+        IndexSpec::new(
+            names.posts,
+            "title_text_content_text",
+            doc! {"title": "text", "content": "text"},
+        ),
+        IndexSpec::new(names.posts, "comments.userId_1", doc! {"comments.userId": 1}),
+        IndexSpec::new(names.posts, "upVotes_1", doc! {"upVotes": 1}),
+        IndexSpec::new(names.refresh_tokens, "tokenHash_1", doc! {"tokenHash": 1}).unique(),
+        IndexSpec::new(names.refresh_tokens, "family_1", doc! {"family": 1}),
+        IndexSpec::new(names.refresh_tokens, "expiresAt_1", doc! {"expiresAt": 1}).expire_at(),
+        IndexSpec::new(names.revoked_tokens, "jti_1", doc! {"jti": 1}).unique(),
+        IndexSpec::new(names.revoked_tokens, "expiresAt_1", doc! {"expiresAt": 1}).expire_at(),
+    ]
+}
+
+/// Creates every missing index from `specs` and prints what was done. An
+/// index that exists under a spec's name but with other keys or options is
+/// an error rather than being taken for the one the application needs.
+pub async fn apply(database: &Database, specs: Vec<IndexSpec>) -> Result<()> {
+    for spec in specs {
+        let collection = database.collection::<Document>(&spec.collection);
+        let existing = existing_indexes(&collection).await?;
+        let found = existing.iter().find(|index| {
+            index.options.as_ref().and_then(|options| options.name.as_deref()) == Some(spec.name)
+        });
+
+        if let Some(index) = found {
+            if !spec.matches(index) {
+                return Err(MyError::IndexMismatchError(format!(
+                    "{}.{} exists with different keys or options, drop it so it can be recreated",
+                    spec.collection, spec.name
+                )));
+            }
+            println!("   index {}.{} already present", spec.collection, spec.name);
+            continue;
+        }
+        let mut options = spec.options;
+        options.name = Some(spec.name.to_string());
+        let index = IndexModel::builder()
+            .keys(spec.keys)
+            .options(options)
+            .build();
+        collection
+            .create_index(index, None)
+            .await
+            .map_err(MyError::query)?;
+        println!("   index {}.{} created", spec.collection, spec.name);
+    }
+    Ok(())
+}
+
+async fn existing_indexes(collection: &Collection<Document>) -> Result<Vec<IndexModel>> {
+    let mut cursor = match collection.list_indexes(None).await {
+        Ok(cursor) => cursor,
+        // A fresh database has no collection yet; it is created along with
+        // its first index.
+        Err(e) if is_namespace_not_found(&e) => return Ok(vec![]),
+        Err(e) => return Err(MyError::query(e)),
+    };
+    let mut indexes = vec![];
+    while let Some(result) = cursor.next().await {
+        indexes.push(result.map_err(MyError::query)?);
+    }
+    Ok(indexes)
+}
+
+fn is_namespace_not_found(error: &mongodb::error::Error) -> bool {
+    matches!(*error.kind, ErrorKind::Command(ref command) if command.code == NAMESPACE_NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(keys: Document, options: IndexOptions) -> IndexModel {
+        IndexModel::builder().keys(keys).options(options).build()
+    }
+
+    #[test]
+    fn unique_flag_must_match() {
+        let spec = IndexSpec::new("users", "email_1", doc! {"email": 1}).unique();
+        let plain = IndexOptions::builder().name("email_1".to_string()).build();
+        let unique = IndexOptions::builder()
+            .name("email_1".to_string())
+            .unique(true)
+            .build();
+
+        assert!(!spec.matches(&listed(doc! {"email": 1}, plain)));
+        assert!(spec.matches(&listed(doc! {"email": 1.0}, unique)));
+    }
+
+    #[test]
+    fn key_order_and_direction_must_match() {
+        let spec = IndexSpec::new("posts", "createdAt_-1__id_-1", doc! {"createdAt": -1, "_id": -1});
+
+        assert!(spec.matches(&listed(doc! {"createdAt": -1, "_id": -1}, IndexOptions::default())));
+        assert!(!spec.matches(&listed(doc! {"_id": -1, "createdAt": -1}, IndexOptions::default())));
+        assert!(!spec.matches(&listed(doc! {"createdAt": 1, "_id": -1}, IndexOptions::default())));
+    }
+
+    #[test]
+    fn text_index_is_matched_on_its_weights() {
+        let spec = IndexSpec::new("posts", "title_text_content_text", doc! {"title": "text", "content": "text"});
+        let keys = doc! {"_fts": "text", "_ftsx": 1};
+        let weights = |weights: Document| IndexOptions::builder().weights(weights).build();
+
+        assert!(spec.matches(&listed(keys.clone(), weights(doc! {"content": 1, "title": 1}))));
+        assert!(!spec.matches(&listed(keys, weights(doc! {"title": 1}))));
+    }
+
+    #[test]
+    fn ttl_must_match() {
+        let spec = IndexSpec::new("revoked", "expiresAt_1", doc! {"expiresAt": 1}).expire_at();
+        let ttl = |secs| IndexOptions::builder().expire_after(Duration::from_secs(secs)).build();
+
+        assert!(spec.matches(&listed(doc! {"expiresAt": 1}, ttl(0))));
+        assert!(!spec.matches(&listed(doc! {"expiresAt": 1}, ttl(3600))));
+        assert!(!spec.matches(&listed(doc! {"expiresAt": 1}, IndexOptions::default())));
+    }
+}
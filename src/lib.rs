@@ -0,0 +1,19 @@
+pub mod auth;
+pub mod config;
+pub mod cors;
+pub mod db;
+pub mod error;
+pub mod extract;
+pub mod handler;
+pub mod index;
+pub mod model;
+pub mod response;
+pub mod route;
+pub mod schema;
+pub mod store;
+
+use db::DB;
+
+pub struct AppState {
+    pub db: DB,
+}
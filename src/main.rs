@@ -1,45 +1,45 @@
-mod db;
-mod error;
-mod handler;
-mod model;
-mod response;
-mod route;
-mod schema;
-
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::http::{
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-    HeaderValue, Method,
-};
-use db::DB;
 use dotenv::dotenv;
-use error::MyError;
-use route::create_router;
-use tower_http::cors::CorsLayer;
-
-pub struct AppState {
-    db: DB,
-}
+use kedubak::{config::Config, cors, db::DB, route::create_router, AppState};
 
 #[tokio::main]
-async fn main() -> Result<(), MyError> {
+async fn main() {
     dotenv().ok();
 
-    let db = DB::init().await?;
-
-    let cors = CorsLayer::new()
-        .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_credentials(true)
-        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
-
-    let app = create_router(Arc::new(AppState { db: db.clone() })).layer(cors);
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("🔥 {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let db = match DB::init(&config).await {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("🔥 {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Tokens are short lived, an hourly sweep keeps the tables small.
+    let store = db.store.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = store.delete_expired_tokens(chrono::Utc::now()).await {
+                eprintln!("🔥 failed to delete expired tokens: {}", e);
+            }
+        }
+    });
+
+    let app = create_router(Arc::new(AppState { db: db.clone() })).layer(cors::layer(&config.cors));
 
     println!("🚀 Server started successfully");
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(config.bind_address).await.unwrap();
     axum::serve(listener, app).await.unwrap();
-
-    Ok(())
 }
 
@@ -15,21 +15,31 @@ pub struct UserModel {
     pub createdAt: DateTime<Utc>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub lastUpVote: DateTime<Utc>,
+    #[serde(default)]
+    pub tokenGeneration: i64,
 }
 
 #[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Comments {
     pub id: String,
+    #[serde(default)]
+    pub userId: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parentId: Option<String>,
     pub firstName: String,
     pub content: String,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub createdAt: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editedAt: Option<bson::DateTime>,
 }
 
 #[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PostModel {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
     pub userId: String,
     pub title: String,
     pub content: String,
@@ -38,12 +48,56 @@ pub struct PostModel {
     pub upVotes: Vec<String>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub createdAt: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updatedAt: Option<bson::DateTime>,
+    #[serde(default)]
+    pub history: Vec<PostRevision>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostRevision {
+    pub title: String,
+    pub content: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub editedAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefreshTokenModel {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub userId: ObjectId,
+    pub family: String,
+    pub tokenHash: String,
+    pub used: bool,
+    pub revoked: bool,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiresAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevokedTokenModel {
+    pub jti: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiresAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Claims {
     pub name: String,
     pub exp: u64,
     pub iat: u64,
+    #[serde(default)]
+    pub jti: String,
+    #[serde(default)]
+    pub gen: i64,
+    /// Refresh token family of the session the token was issued for.
+    #[serde(default)]
+    pub sid: String,
 }
 
@@ -1,7 +1,6 @@
 use chrono::{DateTime, Utc};
 use mongodb::bson::{self};
 use serde::{Serialize, Deserialize};
-use crate::model::Comments;
 
 #[derive(Serialize)]
 pub struct GenericResponse {
@@ -9,6 +8,12 @@ pub struct GenericResponse {
     pub message: String,
 }
 
+#[derive(Serialize, Debug)]
+pub struct MessageResponse {
+    pub ok: bool,
+    pub message: String,
+}
+
 #[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserResponse {
@@ -24,23 +29,80 @@ pub struct UserResponseDel {
     pub firstName: String,
     pub lastName: String,
     pub removed: bool,
+    pub postsRemoved: u64,
+    pub commentsRemoved: u64,
+    pub votesRemoved: u64,
 }
 
+#[allow(non_snake_case)]
 #[derive(Serialize, Debug)]
 pub struct UserData {
     pub token: String,
+    pub refreshToken: String,
     pub user: UserResponse,
 }
 
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct TokenData {
+    pub token: String,
+    pub refreshToken: String,
+}
+
+#[allow(non_snake_case)]
 #[derive(Serialize, Debug)]
 pub struct PostData {
+    #[serde(rename = "_id")]
+    pub id: String,
     pub createdAt: String,
     pub userId: String,
     pub firstName: String,
     pub title: String,
     pub content: String,
-    pub comments: Vec<Comments>,
+    pub comments: Vec<CommentData>,
     pub upVotes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updatedAt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct RevisionData {
+    pub title: String,
+    pub content: String,
+    pub editedAt: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct CommentData {
+    pub id: String,
+    pub userId: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parentId: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+    pub firstName: String,
+    pub content: String,
+    pub createdAt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editedAt: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentDataDel {
+    #[serde(flatten)]
+    pub comment: CommentData,
+    pub removed: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PostDataDel {
+    #[serde(flatten)]
+    pub post: PostData,
+    pub removed: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -56,12 +118,36 @@ pub struct SingleUserResponse {
     pub data: UserData,
 }
 
+#[derive(Serialize, Debug)]
+pub struct PostHistoryResponse {
+    pub ok: bool,
+    pub data: Vec<RevisionData>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleTokenResponse {
+    pub ok: bool,
+    pub data: TokenData,
+}
+
 #[derive(Serialize, Debug)]
 pub struct SinglePostResponse {
     pub ok: bool,
     pub data: PostData,
 }
 
+#[derive(Serialize, Debug)]
+pub struct SingleCommentResponse {
+    pub ok: bool,
+    pub data: CommentData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleCommentResponseDel {
+    pub ok: bool,
+    pub data: CommentDataDel,
+}
+
 #[derive(Serialize, Debug)]
 pub struct SingleUserResponseGet {
     pub ok: bool,
@@ -74,10 +160,23 @@ pub struct SingleUserResponseDel {
     pub data: UserResponseDel,
 }
 
+#[derive(Serialize, Debug)]
+pub struct SinglePostResponseDel {
+    pub ok: bool,
+    pub data: PostDataDel,
+}
+
 #[derive(Serialize, Debug)]
 pub struct SinglePostResponseGet {
     pub ok: bool,
     pub data: Vec<PostData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u64>,
+    pub limit: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -12,6 +12,12 @@ use crate::{
         post_handler, get_user_post_handler,
         get_id_post_handler, login_handler,
         edit_user_handler, delete_user_handler,
+        delete_post_handler, vote_handler,
+        comment_handler, refresh_handler,
+        logout_handler, logout_all_handler,
+        search_post_handler, edit_post_handler,
+        get_post_history_handler, edit_comment_handler,
+        delete_comment_handler,
     },
     AppState,
 };
@@ -21,13 +27,24 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
         .route("/api/healthchecker", get(health_checker_handler))
         .route("/auth/register", post(register_handler))
         .route("/auth/login", post(login_handler))
+        .route("/auth/refresh", post(refresh_handler))
+        .route("/auth/logout", post(logout_handler))
+        .route("/auth/logout/all", post(logout_all_handler))
         .route("/user/me", get(connected_handler))
         .route("/user/edit", put(edit_user_handler))
         .route("/user/remove", delete(delete_user_handler))
         .route("/post", post(post_handler))
         .route("/post", get(get_post_handler))
         .route("/post/me", get(get_user_post_handler))
+        .route("/post/search", get(search_post_handler))
         .route("/post/:id", get(get_id_post_handler))
+        .route("/post/:id", put(edit_post_handler))
+        .route("/post/:id", delete(delete_post_handler))
+        .route("/post/:id/history", get(get_post_history_handler))
+        .route("/post/vote/:id", post(vote_handler))
+        .route("/comment/:id", post(comment_handler))
+        .route("/comment/:id/:comment_id", put(edit_comment_handler))
+        .route("/comment/:id/:comment_id", delete(delete_comment_handler))
         .with_state(app_state)
 }
 
@@ -1,9 +1,21 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, Default)]
 pub struct FilterOptions {
     pub page: Option<usize>,
     pub limit: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchOptions {
+    pub q: String,
+    pub author: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -25,6 +37,22 @@ pub struct CreatePostSchema {
     pub content: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdatePostSchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateCommentSchema {
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parentId: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserSchema {
     pub email: String,
@@ -38,10 +66,23 @@ pub struct LoginSchema {
     pub password: String,
 }
 
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RefreshSchema {
+    pub refreshToken: String,
+}
+
+#[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UpdateUserSchema {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firstName: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lastName: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currentPassword: Option<String>,
 }
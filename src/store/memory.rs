@@ -0,0 +1,433 @@
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use mongodb::bson::oid::ObjectId;
+
+use super::{
+    CascadeCounts, PostQuery, PostStore, Result, SearchQuery, TokenStore, UserStore, UserUpdate,
+};
+use crate::error::MyError::*;
+use crate::model::{
+    Comments, PostModel, PostRevision, RefreshTokenModel, RevokedTokenModel, UserModel,
+};
+
+/// Store keeping everything in process memory. Data is lost on restart; it
+/// exists so the API can be exercised without a database.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    data: Mutex<Data>,
+}
+
+#[derive(Debug, Default)]
+struct Data {
+    users: Vec<UserModel>,
+    posts: Vec<PostModel>,
+    refresh_tokens: Vec<RefreshTokenModel>,
+    revoked_tokens: Vec<RevokedTokenModel>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn data(&self) -> MutexGuard<'_, Data> {
+        self.data.lock().expect("memory store lock poisoned")
+    }
+}
+
+impl Data {
+    fn user_mut(&mut self, id: ObjectId) -> Option<&mut UserModel> {
+        self.users.iter_mut().find(|user| user.id == id)
+    }
+
+    fn post_mut(&mut self, id: ObjectId) -> Option<&mut PostModel> {
+        self.posts.iter_mut().find(|post| post.id == Some(id))
+    }
+}
+
+/// Sort key matching the `(createdAt, _id)` index. BSON dates only keep
+/// milliseconds, and cursors are built from them.
+fn sort_key(post: &PostModel) -> (i64, Option<ObjectId>) {
+    (post.createdAt.timestamp_millis(), post.id)
+}
+
+fn sort_newest_first(posts: &mut [PostModel]) {
+    posts.sort_by_key(|post| std::cmp::Reverse(sort_key(post)));
+}
+
+fn page<T>(items: Vec<T>, skip: u64, limit: i64) -> Vec<T> {
+    let iter = items.into_iter().skip(skip as usize);
+    match usize::try_from(limit) {
+        Ok(limit) if limit > 0 => iter.take(limit).collect(),
+        _ => iter.collect(),
+    }
+}
+
+/// Rough stand-in for MongoDB's text score: how often the search terms occur
+/// in the title and content, ignoring case.
+fn text_score(post: &PostModel, terms: &[String]) -> f64 {
+    let text = format!("{} {}", post.title, post.content).to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    terms
+        .iter()
+        .map(|term| words.iter().filter(|word| *word == term).count() as f64)
+        .sum()
+}
+
+#[async_trait]
+impl UserStore for MemoryStore {
+    async fn insert_user(&self, user: &UserModel) -> Result<()> {
+        let mut data = self.data();
+        if data.users.iter().any(|u| u.email == user.email || u.id == user.id) {
+            return Err(DuplicateError("email".to_string()));
+        }
+        data.users.push(user.clone());
+        Ok(())
+    }
+
+    async fn find_user(&self, id: ObjectId) -> Result<Option<UserModel>> {
+        Ok(self.data().users.iter().find(|user| user.id == id).cloned())
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<UserModel>> {
+        Ok(self.data().users.iter().find(|user| user.email == email).cloned())
+    }
+
+    async fn update_user(&self, id: ObjectId, update: &UserUpdate) -> Result<Option<UserModel>> {
+        let mut data = self.data();
+        if let Some(email) = &update.email {
+            if data.users.iter().any(|u| &u.email == email && u.id != id) {
+                return Err(DuplicateError("email".to_string()));
+            }
+        }
+        let user = match data.user_mut(id) {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+        for (field, value) in [
+            (&mut user.email, &update.email),
+            (&mut user.firstName, &update.first_name),
+            (&mut user.lastName, &update.last_name),
+            (&mut user.password, &update.password),
+        ] {
+            if let Some(value) = value {
+                *field = value.to_string();
+            }
+        }
+        Ok(Some(user.clone()))
+    }
+
+    async fn replace_password(&self, id: ObjectId, old_hash: &str, new_hash: &str) -> Result<()> {
+        if let Some(user) = self.data().user_mut(id) {
+            if user.password == old_hash {
+                user.password = new_hash.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    async fn claim_upvote(
+        &self,
+        id: ObjectId,
+        cooldown_end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        match self.data().user_mut(id) {
+            Some(user) if user.lastUpVote <= cooldown_end => {
+                user.lastUpVote = now;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn release_upvote(
+        &self,
+        id: ObjectId,
+        claimed_at: DateTime<Utc>,
+        restore_to: DateTime<Utc>,
+    ) -> Result<()> {
+        if let Some(user) = self.data().user_mut(id) {
+            if user.lastUpVote == claimed_at {
+                user.lastUpVote = restore_to;
+            }
+        }
+        Ok(())
+    }
+
+    async fn bump_token_generation(&self, id: ObjectId) -> Result<bool> {
+        match self.data().user_mut(id) {
+            Some(user) => {
+                user.tokenGeneration += 1;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete_user_cascade(&self, id: ObjectId) -> Result<Option<CascadeCounts>> {
+        let mut data = self.data();
+        let before = data.users.len();
+        data.users.retain(|user| user.id != id);
+        if data.users.len() == before {
+            return Ok(None);
+        }
+
+        let author = id.to_string();
+        let mut counts = CascadeCounts::default();
+        let before = data.posts.len();
+        data.posts.retain(|post| post.userId != author);
+        counts.posts = (before - data.posts.len()) as u64;
+        for post in data.posts.iter_mut() {
+            let before = post.comments.len();
+            post.comments.retain(|comment| comment.userId != author);
+            counts.comments += (before - post.comments.len()) as u64;
+
+            let before = post.upVotes.len();
+            post.upVotes.retain(|voter| voter != &author);
+            counts.votes += (before - post.upVotes.len()) as u64;
+        }
+        Ok(Some(counts))
+    }
+}
+
+#[async_trait]
+impl PostStore for MemoryStore {
+    async fn insert_post(&self, post: &PostModel) -> Result<()> {
+        let mut data = self.data();
+        if post.id.is_none() || data.posts.iter().any(|p| p.id == post.id) {
+            return Err(DuplicateError("_id".to_string()));
+        }
+        data.posts.push(post.clone());
+        Ok(())
+    }
+
+    async fn find_post(&self, id: ObjectId) -> Result<Option<PostModel>> {
+        Ok(self.data().posts.iter().find(|post| post.id == Some(id)).cloned())
+    }
+
+    async fn delete_post(&self, id: ObjectId) -> Result<bool> {
+        let mut data = self.data();
+        let before = data.posts.len();
+        data.posts.retain(|post| post.id != Some(id));
+        Ok(data.posts.len() != before)
+    }
+
+    async fn count_posts(&self, author: Option<&str>) -> Result<u64> {
+        let data = self.data();
+        let count = data
+            .posts
+            .iter()
+            .filter(|post| author.is_none_or(|author| post.userId == author))
+            .count();
+        Ok(count as u64)
+    }
+
+    async fn list_posts(&self, query: &PostQuery) -> Result<Vec<PostModel>> {
+        let mut posts: Vec<PostModel> = self
+            .data()
+            .posts
+            .iter()
+            .filter(|post| query.author.as_ref().is_none_or(|author| &post.userId == author))
+            .filter(|post| match query.after {
+                Some((created_at, id)) => {
+                    sort_key(post) < (created_at.timestamp_millis(), Some(id))
+                }
+                None => true,
+            })
+            .cloned()
+            .collect();
+        sort_newest_first(&mut posts);
+        Ok(page(posts, query.skip, query.limit))
+    }
+
+    async fn search_posts(&self, query: &SearchQuery) -> Result<(u64, Vec<(PostModel, f64)>)> {
+        let terms: Vec<String> = query
+            .text
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+        let mut posts: Vec<PostModel> = self
+            .data()
+            .posts
+            .iter()
+            .filter(|post| query.author.as_ref().is_none_or(|author| &post.userId == author))
+            .filter(|post| query.from.is_none_or(|from| post.createdAt >= from))
+            .filter(|post| query.to.is_none_or(|to| post.createdAt <= to))
+            .cloned()
+            .collect();
+        sort_newest_first(&mut posts);
+
+        let mut scored: Vec<(PostModel, f64)> = posts
+            .into_iter()
+            .map(|post| {
+                let score = text_score(&post, &terms);
+                (post, score)
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        // Stable sort, so equal scores stay newest first.
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok((scored.len() as u64, page(scored, query.skip, query.limit)))
+    }
+
+    async fn add_vote(&self, post_id: ObjectId, voter: &str) -> Result<bool> {
+        match self.data().post_mut(post_id) {
+            Some(post) if !post.upVotes.iter().any(|v| v == voter) => {
+                post.upVotes.push(voter.to_string());
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn push_comment(&self, post_id: ObjectId, comment: &Comments) -> Result<bool> {
+        let mut data = self.data();
+        let post = match data.post_mut(post_id) {
+            Some(post) => post,
+            None => return Ok(false),
+        };
+        if let Some(parent_id) = &comment.parentId {
+            if !post.comments.iter().any(|c| &c.id == parent_id) {
+                return Ok(false);
+            }
+        }
+        post.comments.push(comment.clone());
+        Ok(true)
+    }
+
+    async fn update_comment(
+        &self,
+        post_id: ObjectId,
+        comment_id: &str,
+        content: &str,
+        edited_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let mut data = self.data();
+        let comment = data
+            .post_mut(post_id)
+            .and_then(|post| post.comments.iter_mut().find(|c| c.id == comment_id));
+        match comment {
+            Some(comment) => {
+                comment.content = content.to_string();
+                comment.editedAt = Some(edited_at.into());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn remove_comment(&self, post_id: ObjectId, comment_id: &str) -> Result<bool> {
+        match self.data().post_mut(post_id) {
+            Some(post) => {
+                let before = post.comments.len();
+                post.comments.retain(|c| c.id != comment_id);
+                Ok(post.comments.len() != before)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn update_post(
+        &self,
+        post_id: ObjectId,
+        previous: &PostRevision,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<PostModel>> {
+        match self.data().post_mut(post_id) {
+            Some(post) if post.title == previous.title && post.content == previous.content => {
+                post.title = title.to_string();
+                post.content = content.to_string();
+                post.updatedAt = Some(previous.editedAt.into());
+                post.history.push(previous.clone());
+                Ok(Some(post.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn rename_author(&self, user_id: &str, first_name: &str) -> Result<()> {
+        for post in self.data().posts.iter_mut() {
+            if post.userId == user_id {
+                post.firstName = first_name.to_string();
+            }
+            for comment in post.comments.iter_mut().filter(|c| c.userId == user_id) {
+                comment.firstName = first_name.to_string();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TokenStore for MemoryStore {
+    async fn insert_refresh_token(&self, token: &RefreshTokenModel) -> Result<()> {
+        let mut data = self.data();
+        if data.refresh_tokens.iter().any(|t| t.tokenHash == token.tokenHash) {
+            return Err(DuplicateError("tokenHash".to_string()));
+        }
+        data.refresh_tokens.push(token.clone());
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>> {
+        let mut data = self.data();
+        let token = data
+            .refresh_tokens
+            .iter_mut()
+            .find(|t| t.tokenHash == token_hash && !t.used && !t.revoked);
+        match token {
+            Some(token) => {
+                let previous = token.clone();
+                token.used = true;
+                Ok(Some(previous))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>> {
+        let data = self.data();
+        Ok(data.refresh_tokens.iter().find(|t| t.tokenHash == token_hash).cloned())
+    }
+
+    async fn revoke_refresh_family(&self, family: &str) -> Result<()> {
+        for token in self.data().refresh_tokens.iter_mut().filter(|t| t.family == family) {
+            token.revoked = true;
+        }
+        Ok(())
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: ObjectId) -> Result<()> {
+        for token in self.data().refresh_tokens.iter_mut().filter(|t| t.userId == user_id) {
+            token.revoked = true;
+        }
+        Ok(())
+    }
+
+    async fn revoke_token(&self, token: &RevokedTokenModel) -> Result<()> {
+        let mut data = self.data();
+        if data.revoked_tokens.iter().any(|t| t.jti == token.jti) {
+            return Err(DuplicateError("jti".to_string()));
+        }
+        data.revoked_tokens.push(token.clone());
+        Ok(())
+    }
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool> {
+        Ok(self.data().revoked_tokens.iter().any(|t| t.jti == jti))
+    }
+
+    async fn delete_expired_tokens(&self, now: DateTime<Utc>) -> Result<()> {
+        let mut data = self.data();
+        data.refresh_tokens.retain(|t| t.expiresAt >= now);
+        data.revoked_tokens.retain(|t| t.expiresAt >= now);
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+pub mod memory;
+pub mod mongo;
+pub mod mysql;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+use crate::error::MyError;
+use crate::model::{
+    Comments, PostModel, PostRevision, RefreshTokenModel, RevokedTokenModel, UserModel,
+};
+
+pub type Result<T> = std::result::Result<T, MyError>;
+
+/// Fields of a user that `UserStore::update_user` overwrites when set.
+/// `password` is expected to be hashed already.
+#[derive(Debug, Default, Clone)]
+pub struct UserUpdate {
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub password: Option<String>,
+}
+
+impl UserUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.email.is_none()
+            && self.first_name.is_none()
+            && self.last_name.is_none()
+            && self.password.is_none()
+    }
+}
+
+/// What `UserStore::delete_user_cascade` removed besides the user itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CascadeCounts {
+    pub posts: u64,
+    pub comments: u64,
+    pub votes: u64,
+}
+
+/// Page of posts sorted by `(createdAt, _id)` descending.
+#[derive(Debug, Default, Clone)]
+pub struct PostQuery {
+    pub author: Option<String>,
+    /// Only return posts strictly older than this `(createdAt, _id)` key.
+    pub after: Option<(DateTime<Utc>, ObjectId)>,
+    pub skip: u64,
+    pub limit: i64,
+}
+
+/// Full-text query over post titles and contents, best matches first.
+#[derive(Debug, Default, Clone)]
+pub struct SearchQuery {
+    pub text: String,
+    pub author: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub skip: u64,
+    pub limit: i64,
+}
+
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    /// Fails with a duplicate error when the email is already taken.
+    async fn insert_user(&self, user: &UserModel) -> Result<()>;
+
+    async fn find_user(&self, id: ObjectId) -> Result<Option<UserModel>>;
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<UserModel>>;
+
+    /// Applies `update` and returns the updated user, or `None` when it does
+    /// not exist. Fails with a duplicate error when the new email is taken.
+    async fn update_user(&self, id: ObjectId, update: &UserUpdate) -> Result<Option<UserModel>>;
+
+    /// Swaps the password hash only if it is still `old_hash`.
+    async fn replace_password(&self, id: ObjectId, old_hash: &str, new_hash: &str) -> Result<()>;
+
+    /// Sets `lastUpVote` to `now` if it is not later than `cooldown_end`.
+    /// Returns whether the slot was claimed.
+    async fn claim_upvote(
+        &self,
+        id: ObjectId,
+        cooldown_end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<bool>;
+
+    /// Undoes a `claim_upvote` made at `claimed_at` by setting `lastUpVote`
+    /// to `restore_to`, unless a later claim has replaced it since.
+    async fn release_upvote(
+        &self,
+        id: ObjectId,
+        claimed_at: DateTime<Utc>,
+        restore_to: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// Increments `tokenGeneration`. Returns false when the user is unknown.
+    async fn bump_token_generation(&self, id: ObjectId) -> Result<bool>;
+
+    /// Atomically deletes the user, their posts, and their comments and votes
+    /// on other posts. Returns `None` when the user does not exist.
+    async fn delete_user_cascade(&self, id: ObjectId) -> Result<Option<CascadeCounts>>;
+}
+
+#[async_trait]
+pub trait PostStore: Send + Sync {
+    /// Stores `post`, whose `id` must already be set.
+    async fn insert_post(&self, post: &PostModel) -> Result<()>;
+
+    async fn find_post(&self, id: ObjectId) -> Result<Option<PostModel>>;
+
+    async fn delete_post(&self, id: ObjectId) -> Result<bool>;
+
+    async fn count_posts(&self, author: Option<&str>) -> Result<u64>;
+
+    async fn list_posts(&self, query: &PostQuery) -> Result<Vec<PostModel>>;
+
+    /// Returns the total number of matches and the requested page with the
+    /// relevance score of each post.
+    async fn search_posts(&self, query: &SearchQuery) -> Result<(u64, Vec<(PostModel, f64)>)>;
+
+    /// Adds `voter` to `upVotes`. Returns false when the post is unknown or
+    /// `voter` already voted.
+    async fn add_vote(&self, post_id: ObjectId, voter: &str) -> Result<bool>;
+
+    /// Appends `comment`. When it has a `parentId` the parent must be on the
+    /// same post. Returns false when the post or the parent is missing.
+    async fn push_comment(&self, post_id: ObjectId, comment: &Comments) -> Result<bool>;
+
+    async fn update_comment(
+        &self,
+        post_id: ObjectId,
+        comment_id: &str,
+        content: &str,
+        edited_at: DateTime<Utc>,
+    ) -> Result<bool>;
+
+    async fn remove_comment(&self, post_id: ObjectId, comment_id: &str) -> Result<bool>;
+
+    /// Sets the new title and content and appends `previous` to the history,
+    /// only if the stored title and content still match `previous`.
+    async fn update_post(
+        &self,
+        post_id: ObjectId,
+        previous: &PostRevision,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<PostModel>>;
+
+    /// Rewrites the author name copied into posts and comments at write time.
+    async fn rename_author(&self, user_id: &str, first_name: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    async fn insert_refresh_token(&self, token: &RefreshTokenModel) -> Result<()>;
+
+    /// Marks the unused, unrevoked token with this hash as used and returns
+    /// it. Only one caller can consume a given token.
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>>;
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>>;
+
+    async fn revoke_refresh_family(&self, family: &str) -> Result<()>;
+
+    async fn revoke_user_refresh_tokens(&self, user_id: ObjectId) -> Result<()>;
+
+    async fn revoke_token(&self, token: &RevokedTokenModel) -> Result<()>;
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool>;
+
+    /// Drops refresh tokens and revoked access tokens that expired before
+    /// `now`. Backends with TTL indexes may leave this to the database.
+    async fn delete_expired_tokens(&self, now: DateTime<Utc>) -> Result<()>;
+}
+
+/// Everything `DB` needs from a storage backend.
+pub trait Store: UserStore + PostStore + TokenStore {}
+
+impl<T: UserStore + PostStore + TokenStore> Store for T {}
@@ -0,0 +1,585 @@
+use async_trait::async_trait;
+use chrono::prelude::*;
+use futures::StreamExt;
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+use mongodb::options::{
+    ClientOptions, FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions,
+};
+use mongodb::{Client, ClientSession, Collection};
+
+use super::{
+    CascadeCounts, PostQuery, PostStore, Result, SearchQuery, TokenStore, UserStore, UserUpdate,
+};
+use crate::config::{DatabaseConfig, MongoConfig};
+use crate::error::MyError::{self, *};
+use crate::index;
+use crate::model::{
+    Comments, PostModel, PostRevision, RefreshTokenModel, RevokedTokenModel, UserModel,
+};
+
+#[derive(Clone, Debug)]
+pub struct MongoStore {
+    pub client: Client,
+    pub user_collection: Collection<UserModel>,
+    pub post_collection: Collection<PostModel>,
+    pub post_collection_doc: Collection<Document>,
+    pub refresh_collection: Collection<RefreshTokenModel>,
+    pub revoked_collection: Collection<RevokedTokenModel>,
+}
+
+/// `delete_user_cascade` runs in a transaction, which a standalone
+/// `mongod` refuses. Checking up front turns that into a startup error
+/// instead of a 500 on the first account deletion.
+async fn require_transactions(client: &Client) -> Result<()> {
+    let hello = client
+        .database("admin")
+        .run_command(doc! { "isMaster": 1 }, None)
+        .await
+        .map_err(MyError::query)?;
+    let replica_set = hello.get_str("setName").is_ok();
+    let mongos = hello.get_str("msg") == Ok("isdbgrid");
+    if !replica_set && !mongos {
+        return Err(UnsupportedDeploymentError(
+            "MongoDB must run as a replica set (e.g. `mongod --replSet rs0` and \
+             `replicaSet=rs0` in DATABASE_URL), transactions are unavailable on a \
+             standalone server"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl MongoStore {
+    pub async fn init(config: &DatabaseConfig, mongo: &MongoConfig) -> Result<Self> {
+        let database_name = &mongo.database;
+        let user_collection_name = &mongo.user_collection;
+        let post_collection_name = &mongo.post_collection;
+        let refresh_collection_name = &mongo.refresh_token_collection;
+        let revoked_collection_name = &mongo.revoked_token_collection;
+
+        let mut client_options = ClientOptions::parse(&config.url).await?;
+        client_options.max_pool_size = Some(config.max_connections);
+        client_options.app_name = Some(database_name.to_string());
+
+        let client = Client::with_options(client_options)?;
+        let database = client.database(database_name.as_str());
+        require_transactions(&client).await?;
+
+        let user_collection = database.collection(user_collection_name.as_str());
+        let post_collection = database.collection(post_collection_name.as_str());
+        let post_collection_doc =
+            database.collection::<Document>(post_collection_name.as_str());
+        let refresh_collection = database.collection(refresh_collection_name.as_str());
+        let revoked_collection = database.collection(revoked_collection_name.as_str());
+
+        println!("✅ Database connected successfully");
+
+        index::apply(&database, index::registry(&index::CollectionNames {
+            users: user_collection_name,
+            posts: post_collection_name,
+            refresh_tokens: refresh_collection_name,
+            revoked_tokens: revoked_collection_name,
+        })).await?;
+        println!("✅ Indexes ready");
+
+        Ok(Self {
+            client,
+            user_collection,
+            post_collection,
+            post_collection_doc,
+            refresh_collection,
+            revoked_collection,
+        })
+    }
+
+    /// Deletes the user and everything pointing at them inside `session`'s
+    /// transaction.
+    async fn remove_cascade(&self, user_id: ObjectId, session: &mut ClientSession)
+        -> Result<Option<CascadeCounts>> {
+        let author = user_id.to_string();
+
+        let posts = self
+            .post_collection
+            .delete_many_with_session(doc! {"userId": &author}, None, session)
+            .await
+            .map_err(MyError::query)?
+            .deleted_count;
+
+        let mut comments = 0;
+        let mut cursor = self
+            .post_collection
+            .find_with_session(doc! {"comments.userId": &author}, None, session)
+            .await
+            .map_err(MyError::query)?;
+        while let Some(result) = cursor.next(session).await {
+            let post = result.map_err(MyError::query)?;
+            comments += post.comments.iter().filter(|c| c.userId == author).count() as u64;
+        }
+        self.post_collection
+            .update_many_with_session(
+                doc! {"comments.userId": &author},
+                doc! {"$pull": {"comments": {"userId": &author}}},
+                None,
+                session,
+            )
+            .await
+            .map_err(MyError::query)?;
+
+        // upVotes holds each voter at most once, so every modified post is
+        // exactly one vote removed.
+        let votes = self
+            .post_collection
+            .update_many_with_session(
+                doc! {"upVotes": &author},
+                doc! {"$pull": {"upVotes": &author}},
+                None,
+                session,
+            )
+            .await
+            .map_err(MyError::query)?
+            .modified_count;
+
+        let result = self
+            .user_collection
+            .delete_one_with_session(doc! {"_id": user_id}, None, session)
+            .await
+            .map_err(MyError::query)?;
+        if result.deleted_count == 0 {
+            return Ok(None);
+        }
+        Ok(Some(CascadeCounts { posts, comments, votes }))
+    }
+}
+
+fn write_error(e: mongodb::error::Error) -> MyError {
+    if e.to_string().contains("E11000 duplicate key error collection") {
+        return MongoDuplicateError(Box::new(e));
+    }
+    MyError::query(e)
+}
+
+#[async_trait]
+impl UserStore for MongoStore {
+    async fn insert_user(&self, user: &UserModel) -> Result<()> {
+        self.user_collection
+            .insert_one(user, None)
+            .await
+            .map_err(write_error)?;
+        Ok(())
+    }
+
+    async fn find_user(&self, id: ObjectId) -> Result<Option<UserModel>> {
+        self.user_collection
+            .find_one(doc! {"_id": id}, None)
+            .await
+            .map_err(MyError::query)
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<UserModel>> {
+        self.user_collection
+            .find_one(doc! {"email": email}, None)
+            .await
+            .map_err(MyError::query)
+    }
+
+    async fn update_user(&self, id: ObjectId, update: &UserUpdate) -> Result<Option<UserModel>> {
+        let mut set = Document::new();
+        for (field, value) in [
+            ("email", &update.email),
+            ("firstName", &update.first_name),
+            ("lastName", &update.last_name),
+            ("password", &update.password),
+        ] {
+            if let Some(value) = value {
+                set.insert(field, value.to_string());
+            }
+        }
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.user_collection
+            .find_one_and_update(doc! {"_id": id}, doc! {"$set": set}, options)
+            .await
+            .map_err(write_error)
+    }
+
+    async fn replace_password(&self, id: ObjectId, old_hash: &str, new_hash: &str) -> Result<()> {
+        self.user_collection
+            .update_one(
+                doc! {"_id": id, "password": old_hash},
+                doc! {"$set": {"password": new_hash}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(())
+    }
+
+    async fn claim_upvote(
+        &self,
+        id: ObjectId,
+        cooldown_end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        let claimed = self
+            .user_collection
+            .find_one_and_update(
+                doc! {"_id": id, "lastUpVote": {"$lte": cooldown_end}},
+                doc! {"$set": {"lastUpVote": now}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(claimed.is_some())
+    }
+
+    async fn release_upvote(
+        &self,
+        id: ObjectId,
+        claimed_at: DateTime<Utc>,
+        restore_to: DateTime<Utc>,
+    ) -> Result<()> {
+        self.user_collection
+            .update_one(
+                doc! {"_id": id, "lastUpVote": claimed_at},
+                doc! {"$set": {"lastUpVote": restore_to}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(())
+    }
+
+    async fn bump_token_generation(&self, id: ObjectId) -> Result<bool> {
+        let result = self
+            .user_collection
+            .update_one(
+                doc! {"_id": id},
+                doc! {"$inc": {"tokenGeneration": 1}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn delete_user_cascade(&self, id: ObjectId) -> Result<Option<CascadeCounts>> {
+        let mut session = self.client.start_session(None).await.map_err(MyError::query)?;
+        session.start_transaction(None).await.map_err(MyError::query)?;
+        let counts = match self.remove_cascade(id, &mut session).await {
+            Ok(Some(counts)) => counts,
+            result => {
+                let _ = session.abort_transaction().await;
+                return result;
+            }
+        };
+        session.commit_transaction().await.map_err(MyError::query)?;
+        Ok(Some(counts))
+    }
+}
+
+#[async_trait]
+impl PostStore for MongoStore {
+    async fn insert_post(&self, post: &PostModel) -> Result<()> {
+        self.post_collection
+            .insert_one(post, None)
+            .await
+            .map_err(write_error)?;
+        Ok(())
+    }
+
+    async fn find_post(&self, id: ObjectId) -> Result<Option<PostModel>> {
+        self.post_collection
+            .find_one(doc! {"_id": id}, None)
+            .await
+            .map_err(MyError::query)
+    }
+
+    async fn delete_post(&self, id: ObjectId) -> Result<bool> {
+        let result = self
+            .post_collection
+            .delete_one(doc! {"_id": id}, None)
+            .await
+            .map_err(MyError::query)?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn count_posts(&self, author: Option<&str>) -> Result<u64> {
+        let filter = match author {
+            Some(author) => doc! {"userId": author},
+            None => doc! {},
+        };
+        self.post_collection
+            .count_documents(filter, None)
+            .await
+            .map_err(MyError::query)
+    }
+
+    async fn list_posts(&self, query: &PostQuery) -> Result<Vec<PostModel>> {
+        let mut filter = Document::new();
+        if let Some(author) = &query.author {
+            filter.insert("userId", author);
+        }
+        if let Some((created_at, id)) = query.after {
+            filter.insert("$or", vec![
+                doc! {"createdAt": {"$lt": created_at}},
+                doc! {"createdAt": created_at, "_id": {"$lt": id}},
+            ]);
+        }
+        let options = FindOptions::builder()
+            .sort(doc! {"createdAt": -1, "_id": -1})
+            .skip(query.skip)
+            .limit(query.limit)
+            .build();
+        let mut cursor = self
+            .post_collection
+            .find(filter, options)
+            .await
+            .map_err(MyError::query)?;
+        let mut posts: Vec<PostModel> = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            posts.push(result.map_err(MyError::query)?);
+        }
+        Ok(posts)
+    }
+
+    async fn search_posts(&self, query: &SearchQuery) -> Result<(u64, Vec<(PostModel, f64)>)> {
+        let mut filter = doc! {"$text": {"$search": &query.text}};
+        if let Some(author) = &query.author {
+            filter.insert("userId", author);
+        }
+        let mut created_at = Document::new();
+        if let Some(from) = query.from {
+            created_at.insert("$gte", from);
+        }
+        if let Some(to) = query.to {
+            created_at.insert("$lte", to);
+        }
+        if !created_at.is_empty() {
+            filter.insert("createdAt", created_at);
+        }
+
+        let total = self
+            .post_collection_doc
+            .count_documents(filter.clone(), None)
+            .await
+            .map_err(MyError::query)?;
+        let options = FindOptions::builder()
+            .projection(doc! {"score": {"$meta": "textScore"}})
+            .sort(doc! {"score": {"$meta": "textScore"}, "createdAt": -1})
+            .skip(query.skip)
+            .limit(query.limit)
+            .build();
+        let mut cursor = self
+            .post_collection_doc
+            .find(filter, options)
+            .await
+            .map_err(MyError::query)?;
+        let mut posts = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            let mut document = result.map_err(MyError::query)?;
+            let score = document
+                .remove("score")
+                .and_then(|score| score.as_f64())
+                .unwrap_or_default();
+            let post: PostModel = bson::from_document(document)
+                .map_err(MongoDeserializeBsonError)?;
+            posts.push((post, score));
+        }
+        Ok((total, posts))
+    }
+
+    async fn add_vote(&self, post_id: ObjectId, voter: &str) -> Result<bool> {
+        let result = self
+            .post_collection
+            .update_one(
+                doc! {"_id": post_id, "upVotes": {"$ne": voter}},
+                doc! {"$addToSet": {"upVotes": voter}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn push_comment(&self, post_id: ObjectId, comment: &Comments) -> Result<bool> {
+        let update = doc! {
+            "$push": {
+                "comments": bson::to_bson(comment).map_err(MongoSerializeBsonError)?,
+            },
+        };
+        // Replies only land on a post that still holds their parent.
+        let mut filter = doc! {"_id": post_id};
+        if let Some(parent_id) = &comment.parentId {
+            filter.insert("comments.id", parent_id);
+        }
+        let result = self
+            .post_collection
+            .update_one(filter, update, None)
+            .await
+            .map_err(MyError::query)?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn update_comment(
+        &self,
+        post_id: ObjectId,
+        comment_id: &str,
+        content: &str,
+        edited_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = self
+            .post_collection
+            .update_one(
+                doc! {"_id": post_id, "comments.id": comment_id},
+                doc! {"$set": {
+                    "comments.$.content": content,
+                    "comments.$.editedAt": edited_at,
+                }},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn remove_comment(&self, post_id: ObjectId, comment_id: &str) -> Result<bool> {
+        let result = self
+            .post_collection
+            .update_one(
+                doc! {"_id": post_id},
+                doc! {"$pull": {"comments": {"id": comment_id}}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn update_post(
+        &self,
+        post_id: ObjectId,
+        previous: &PostRevision,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<PostModel>> {
+        let update = doc! {
+            "$set": {
+                "title": title,
+                "content": content,
+                "updatedAt": previous.editedAt,
+            },
+            "$push": {
+                "history": bson::to_bson(previous).map_err(MongoSerializeBsonError)?,
+            },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.post_collection
+            .find_one_and_update(
+                doc! {"_id": post_id, "title": &previous.title, "content": &previous.content},
+                update,
+                options,
+            )
+            .await
+            .map_err(MyError::query)
+    }
+
+    async fn rename_author(&self, user_id: &str, first_name: &str) -> Result<()> {
+        self.post_collection
+            .update_many(
+                doc! {"userId": user_id},
+                doc! {"$set": {"firstName": first_name}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+
+        let options = UpdateOptions::builder()
+            .array_filters(vec![doc! {"comment.userId": user_id}])
+            .build();
+        self.post_collection
+            .update_many(
+                doc! {"comments.userId": user_id},
+                doc! {"$set": {"comments.$[comment].firstName": first_name}},
+                options,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TokenStore for MongoStore {
+    async fn insert_refresh_token(&self, token: &RefreshTokenModel) -> Result<()> {
+        self.refresh_collection
+            .insert_one(token, None)
+            .await
+            .map_err(MyError::query)?;
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>> {
+        self.refresh_collection
+            .find_one_and_update(
+                doc! {"tokenHash": token_hash, "used": false, "revoked": false},
+                doc! {"$set": {"used": true}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)
+    }
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>> {
+        self.refresh_collection
+            .find_one(doc! {"tokenHash": token_hash}, None)
+            .await
+            .map_err(MyError::query)
+    }
+
+    async fn revoke_refresh_family(&self, family: &str) -> Result<()> {
+        self.refresh_collection
+            .update_many(doc! {"family": family}, doc! {"$set": {"revoked": true}}, None)
+            .await
+            .map_err(MyError::query)?;
+        Ok(())
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: ObjectId) -> Result<()> {
+        self.refresh_collection
+            .update_many(
+                doc! {"userId": user_id, "revoked": false},
+                doc! {"$set": {"revoked": true}},
+                None,
+            )
+            .await
+            .map_err(MyError::query)?;
+        Ok(())
+    }
+
+    async fn revoke_token(&self, token: &RevokedTokenModel) -> Result<()> {
+        self.revoked_collection
+            .insert_one(token, None)
+            .await
+            .map_err(MyError::query)?;
+        Ok(())
+    }
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool> {
+        let revoked = self
+            .revoked_collection
+            .find_one(doc! {"jti": jti}, None)
+            .await
+            .map_err(MyError::query)?;
+        Ok(revoked.is_some())
+    }
+
+    async fn delete_expired_tokens(&self, _now: DateTime<Utc>) -> Result<()> {
+        // The `expiresAt_1` TTL indexes already remove expired tokens.
+        Ok(())
+    }
+}
@@ -0,0 +1,779 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use mongodb::bson::oid::ObjectId;
+use sqlx::mysql::{MySql, MySqlPool, MySqlPoolOptions};
+use sqlx::{FromRow, QueryBuilder};
+
+use super::{
+    CascadeCounts, PostQuery, PostStore, Result, SearchQuery, TokenStore, UserStore, UserUpdate,
+};
+use crate::config::DatabaseConfig;
+use crate::error::MyError::{self, *};
+use crate::model::{
+    Comments, PostModel, PostRevision, RefreshTokenModel, RevokedTokenModel, UserModel,
+};
+
+/// Store backed by MySQL. The schema lives in `migrations/` and is applied
+/// on startup.
+#[derive(Clone, Debug)]
+pub struct MySqlStore {
+    pub pool: MySqlPool,
+}
+
+const POST_COLUMNS: &str = "id, user_id, title, content, first_name, created_at, updated_at";
+
+#[derive(FromRow)]
+struct UserRow {
+    id: String,
+    email: String,
+    first_name: String,
+    last_name: String,
+    password: String,
+    created_at: DateTime<Utc>,
+    last_up_vote: DateTime<Utc>,
+    token_generation: i64,
+}
+
+#[derive(FromRow)]
+struct PostRow {
+    id: String,
+    user_id: String,
+    title: String,
+    content: String,
+    first_name: String,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+struct CommentRow {
+    id: String,
+    post_id: String,
+    user_id: String,
+    parent_id: Option<String>,
+    first_name: String,
+    content: String,
+    created_at: DateTime<Utc>,
+    edited_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+struct VoteRow {
+    post_id: String,
+    user_id: String,
+}
+
+#[derive(FromRow)]
+struct RevisionRow {
+    post_id: String,
+    title: String,
+    content: String,
+    edited_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct RefreshTokenRow {
+    user_id: String,
+    family: String,
+    token_hash: String,
+    used: bool,
+    revoked: bool,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+// Row conversions fail with the `sqlx::Error` a bad column would have
+// produced; `?` wraps it into `MyError` at the store boundary.
+fn object_id(hex: &str) -> sqlx::Result<ObjectId> {
+    ObjectId::parse_str(hex).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+fn write_error(e: sqlx::Error) -> MyError {
+    match e.as_database_error() {
+        Some(db_error) if db_error.is_unique_violation() => {
+            DuplicateError(db_error.message().to_string())
+        }
+        _ => SqlError(e),
+    }
+}
+
+impl UserRow {
+    fn into_model(self) -> sqlx::Result<UserModel> {
+        Ok(UserModel {
+            id: object_id(&self.id)?,
+            email: self.email,
+            firstName: self.first_name,
+            lastName: self.last_name,
+            password: self.password,
+            createdAt: self.created_at,
+            lastUpVote: self.last_up_vote,
+            tokenGeneration: self.token_generation,
+        })
+    }
+}
+
+impl RefreshTokenRow {
+    fn into_model(self) -> sqlx::Result<RefreshTokenModel> {
+        Ok(RefreshTokenModel {
+            id: None,
+            userId: object_id(&self.user_id)?,
+            family: self.family,
+            tokenHash: self.token_hash,
+            used: self.used,
+            revoked: self.revoked,
+            createdAt: self.created_at,
+            expiresAt: self.expires_at,
+        })
+    }
+}
+
+impl MySqlStore {
+    pub async fn init(config: &DatabaseConfig) -> Result<Self> {
+        let pool = MySqlPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(&config.url)
+            .await?;
+        println!("✅ Database connected successfully");
+
+        sqlx::migrate!().run(&pool).await?;
+        println!("✅ Migrations applied");
+
+        Ok(Self { pool })
+    }
+
+    async fn find_user_where(&self, column: &str, value: &str) -> Result<Option<UserModel>> {
+        let row: Option<UserRow> = sqlx::query_as(&format!(
+            "SELECT id, email, first_name, last_name, password, created_at, last_up_vote, \
+             token_generation FROM users WHERE {} = ?",
+            column
+        ))
+        .bind(value)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(UserRow::into_model).transpose()?)
+    }
+
+    /// Loads the comments, votes and history of `rows` and assembles them
+    /// into posts, keeping the order of `rows`.
+    async fn load_posts(&self, rows: Vec<PostRow>) -> Result<Vec<PostModel>> {
+        if rows.is_empty() {
+            return Ok(vec![]);
+        }
+        let ids: Vec<&str> = rows.iter().map(|row| row.id.as_str()).collect();
+
+        let mut comments: HashMap<String, Vec<Comments>> = HashMap::new();
+        let comment_rows: Vec<CommentRow> = in_ids(
+            "SELECT id, post_id, user_id, parent_id, first_name, content, created_at, edited_at \
+             FROM comments WHERE post_id IN ",
+            &ids,
+            " ORDER BY seq",
+        )
+        .build_query_as()
+        .fetch_all(&self.pool)
+        .await?;
+        for row in comment_rows {
+            comments.entry(row.post_id).or_default().push(Comments {
+                id: row.id,
+                userId: row.user_id,
+                parentId: row.parent_id,
+                firstName: row.first_name,
+                content: row.content,
+                createdAt: row.created_at,
+                editedAt: row.edited_at.map(Into::into),
+            });
+        }
+
+        let mut votes: HashMap<String, Vec<String>> = HashMap::new();
+        let vote_rows: Vec<VoteRow> =
+            in_ids("SELECT post_id, user_id FROM votes WHERE post_id IN ", &ids, " ORDER BY seq")
+                .build_query_as()
+                .fetch_all(&self.pool)
+                .await?;
+        for row in vote_rows {
+            votes.entry(row.post_id).or_default().push(row.user_id);
+        }
+
+        let mut history: HashMap<String, Vec<PostRevision>> = HashMap::new();
+        let revision_rows: Vec<RevisionRow> = in_ids(
+            "SELECT post_id, title, content, edited_at FROM post_revisions WHERE post_id IN ",
+            &ids,
+            " ORDER BY seq",
+        )
+        .build_query_as()
+        .fetch_all(&self.pool)
+        .await?;
+        for row in revision_rows {
+            history.entry(row.post_id).or_default().push(PostRevision {
+                title: row.title,
+                content: row.content,
+                editedAt: row.edited_at,
+            });
+        }
+
+        let posts: sqlx::Result<Vec<PostModel>> = rows
+            .into_iter()
+            .map(|row| {
+                Ok(PostModel {
+                    id: Some(object_id(&row.id)?),
+                    comments: comments.remove(&row.id).unwrap_or_default(),
+                    upVotes: votes.remove(&row.id).unwrap_or_default(),
+                    history: history.remove(&row.id).unwrap_or_default(),
+                    userId: row.user_id,
+                    title: row.title,
+                    content: row.content,
+                    firstName: row.first_name,
+                    createdAt: row.created_at,
+                    updatedAt: row.updated_at.map(Into::into),
+                })
+            })
+            .collect();
+        Ok(posts?)
+    }
+}
+
+/// Builds `prefix (?, ?, ...) suffix` with one bound parameter per id.
+fn in_ids<'a>(prefix: &str, ids: &[&'a str], suffix: &str) -> QueryBuilder<'a, MySql> {
+    let mut builder = QueryBuilder::new(prefix);
+    builder.push("(");
+    let mut separated = builder.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    builder.push(")").push(suffix);
+    builder
+}
+
+#[async_trait]
+impl UserStore for MySqlStore {
+    async fn insert_user(&self, user: &UserModel) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO users (id, email, first_name, last_name, password, created_at, \
+             last_up_vote, token_generation) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(user.id.to_hex())
+        .bind(&user.email)
+        .bind(&user.firstName)
+        .bind(&user.lastName)
+        .bind(&user.password)
+        .bind(user.createdAt)
+        .bind(user.lastUpVote)
+        .bind(user.tokenGeneration)
+        .execute(&self.pool)
+        .await
+        .map_err(write_error)?;
+        Ok(())
+    }
+
+    async fn find_user(&self, id: ObjectId) -> Result<Option<UserModel>> {
+        self.find_user_where("id", &id.to_hex()).await
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<UserModel>> {
+        self.find_user_where("email", email).await
+    }
+
+    async fn update_user(&self, id: ObjectId, update: &UserUpdate) -> Result<Option<UserModel>> {
+        if !update.is_empty() {
+            let mut builder: QueryBuilder<MySql> = QueryBuilder::new("UPDATE users SET ");
+            let mut separated = builder.separated(", ");
+            for (column, value) in [
+                ("email", &update.email),
+                ("first_name", &update.first_name),
+                ("last_name", &update.last_name),
+                ("password", &update.password),
+            ] {
+                if let Some(value) = value {
+                    separated.push(format!("{} = ", column)).push_bind_unseparated(value);
+                }
+            }
+            builder.push(" WHERE id = ").push_bind(id.to_hex());
+            builder
+                .build()
+                .execute(&self.pool)
+                .await
+                .map_err(write_error)?;
+        }
+        self.find_user(id).await
+    }
+
+    async fn replace_password(&self, id: ObjectId, old_hash: &str, new_hash: &str) -> Result<()> {
+        sqlx::query("UPDATE users SET password = ? WHERE id = ? AND password = ?")
+            .bind(new_hash)
+            .bind(id.to_hex())
+            .bind(old_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn claim_upvote(
+        &self,
+        id: ObjectId,
+        cooldown_end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result =
+            sqlx::query("UPDATE users SET last_up_vote = ? WHERE id = ? AND last_up_vote <= ?")
+                .bind(now)
+                .bind(id.to_hex())
+                .bind(cooldown_end)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn release_upvote(
+        &self,
+        id: ObjectId,
+        claimed_at: DateTime<Utc>,
+        restore_to: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query("UPDATE users SET last_up_vote = ? WHERE id = ? AND last_up_vote = ?")
+            .bind(restore_to)
+            .bind(id.to_hex())
+            .bind(claimed_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn bump_token_generation(&self, id: ObjectId) -> Result<bool> {
+        let result =
+            sqlx::query("UPDATE users SET token_generation = token_generation + 1 WHERE id = ?")
+                .bind(id.to_hex())
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_user_cascade(&self, id: ObjectId) -> Result<Option<CascadeCounts>> {
+        let author = id.to_hex();
+        let mut tx = self.pool.begin().await?;
+
+        let exists: Option<(String,)> =
+            sqlx::query_as("SELECT id FROM users WHERE id = ? FOR UPDATE")
+                .bind(&author)
+                .fetch_optional(&mut *tx)
+                .await?;
+        if exists.is_none() {
+            return Ok(None);
+        }
+        // Comments and votes on the user's own posts go with the posts; the
+        // counts only cover what they left on other posts.
+        let posts = sqlx::query("DELETE FROM posts WHERE user_id = ?")
+            .bind(&author)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let comments = sqlx::query("DELETE FROM comments WHERE user_id = ?")
+            .bind(&author)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let votes = sqlx::query("DELETE FROM votes WHERE user_id = ?")
+            .bind(&author)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(&author)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(Some(CascadeCounts { posts, comments, votes }))
+    }
+}
+
+#[async_trait]
+impl PostStore for MySqlStore {
+    async fn insert_post(&self, post: &PostModel) -> Result<()> {
+        let post_id = match post.id {
+            Some(id) => id,
+            None => return Err(BadRequestError("post id must be set".to_string())),
+        };
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO posts (id, user_id, title, content, first_name, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(post_id.to_hex())
+        .bind(&post.userId)
+        .bind(&post.title)
+        .bind(&post.content)
+        .bind(&post.firstName)
+        .bind(post.createdAt)
+        .bind(post.updatedAt.map(|date| date.to_chrono()))
+        .execute(&mut *tx)
+        .await
+        .map_err(write_error)?;
+        for comment in &post.comments {
+            sqlx::query(
+                "INSERT INTO comments (id, post_id, user_id, parent_id, first_name, content, \
+                 created_at, edited_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&comment.id)
+            .bind(post_id.to_hex())
+            .bind(&comment.userId)
+            .bind(&comment.parentId)
+            .bind(&comment.firstName)
+            .bind(&comment.content)
+            .bind(comment.createdAt)
+            .bind(comment.editedAt.map(|date| date.to_chrono()))
+            .execute(&mut *tx)
+            .await
+            .map_err(write_error)?;
+        }
+        for voter in &post.upVotes {
+            sqlx::query("INSERT INTO votes (post_id, user_id, created_at) VALUES (?, ?, ?)")
+                .bind(post_id.to_hex())
+                .bind(voter)
+                .bind(post.createdAt)
+                .execute(&mut *tx)
+                .await
+                .map_err(write_error)?;
+        }
+        for revision in &post.history {
+            sqlx::query(
+                "INSERT INTO post_revisions (post_id, title, content, edited_at) \
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(post_id.to_hex())
+            .bind(&revision.title)
+            .bind(&revision.content)
+            .bind(revision.editedAt)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find_post(&self, id: ObjectId) -> Result<Option<PostModel>> {
+        let row: Option<PostRow> =
+            sqlx::query_as(&format!("SELECT {} FROM posts WHERE id = ?", POST_COLUMNS))
+                .bind(id.to_hex())
+                .fetch_optional(&self.pool)
+                .await?;
+        let rows = row.into_iter().collect();
+        Ok(self.load_posts(rows).await?.pop())
+    }
+
+    async fn delete_post(&self, id: ObjectId) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM posts WHERE id = ?")
+            .bind(id.to_hex())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_posts(&self, author: Option<&str>) -> Result<u64> {
+        let (count,): (i64,) = match author {
+            Some(author) => {
+                sqlx::query_as("SELECT COUNT(*) FROM posts WHERE user_id = ?")
+                    .bind(author)
+                    .fetch_one(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query_as("SELECT COUNT(*) FROM posts")
+                    .fetch_one(&self.pool)
+                    .await?
+            }
+        };
+        Ok(count as u64)
+    }
+
+    async fn list_posts(&self, query: &PostQuery) -> Result<Vec<PostModel>> {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new(format!("SELECT {} FROM posts WHERE TRUE", POST_COLUMNS));
+        if let Some(author) = &query.author {
+            builder.push(" AND user_id = ").push_bind(author);
+        }
+        if let Some((created_at, id)) = query.after {
+            builder
+                .push(" AND (created_at < ")
+                .push_bind(created_at)
+                .push(" OR (created_at = ")
+                .push_bind(created_at)
+                .push(" AND id < ")
+                .push_bind(id.to_hex())
+                .push("))");
+        }
+        builder
+            .push(" ORDER BY created_at DESC, id DESC LIMIT ")
+            .push_bind(query.limit)
+            .push(" OFFSET ")
+            .push_bind(query.skip);
+
+        let rows: Vec<PostRow> = builder.build_query_as().fetch_all(&self.pool).await?;
+        self.load_posts(rows).await
+    }
+
+    async fn search_posts(&self, query: &SearchQuery) -> Result<(u64, Vec<(PostModel, f64)>)> {
+        let filter = |builder: &mut QueryBuilder<MySql>| {
+            builder
+                .push(" WHERE MATCH (title, content) AGAINST (")
+                .push_bind(query.text.to_string())
+                .push(" IN NATURAL LANGUAGE MODE)");
+            if let Some(author) = &query.author {
+                builder.push(" AND user_id = ").push_bind(author.to_string());
+            }
+            if let Some(from) = query.from {
+                builder.push(" AND created_at >= ").push_bind(from);
+            }
+            if let Some(to) = query.to {
+                builder.push(" AND created_at <= ").push_bind(to);
+            }
+        };
+
+        let mut count: QueryBuilder<MySql> = QueryBuilder::new("SELECT COUNT(*) FROM posts");
+        filter(&mut count);
+        let (total,): (i64,) = count.build_query_as().fetch_one(&self.pool).await?;
+
+        let mut select: QueryBuilder<MySql> = QueryBuilder::new(format!(
+            "SELECT {}, MATCH (title, content) AGAINST (",
+            POST_COLUMNS
+        ));
+        select
+            .push_bind(query.text.to_string())
+            .push(" IN NATURAL LANGUAGE MODE) AS score FROM posts");
+        filter(&mut select);
+        select
+            .push(" ORDER BY score DESC, created_at DESC LIMIT ")
+            .push_bind(query.limit)
+            .push(" OFFSET ")
+            .push_bind(query.skip);
+
+        #[derive(FromRow)]
+        struct ScoredRow {
+            #[sqlx(flatten)]
+            post: PostRow,
+            score: f64,
+        }
+        let rows: Vec<ScoredRow> = select.build_query_as().fetch_all(&self.pool).await?;
+        let scores: Vec<f64> = rows.iter().map(|row| row.score).collect();
+        let posts = self
+            .load_posts(rows.into_iter().map(|row| row.post).collect())
+            .await?;
+        Ok((total as u64, posts.into_iter().zip(scores).collect()))
+    }
+
+    async fn add_vote(&self, post_id: ObjectId, voter: &str) -> Result<bool> {
+        // Selecting from posts makes a vote on a missing post a no-op, and
+        // IGNORE does the same for a repeated one.
+        let result = sqlx::query(
+            "INSERT IGNORE INTO votes (post_id, user_id, created_at) \
+             SELECT id, ?, ? FROM posts WHERE id = ?",
+        )
+        .bind(voter)
+        .bind(Utc::now())
+        .bind(post_id.to_hex())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn push_comment(&self, post_id: ObjectId, comment: &Comments) -> Result<bool> {
+        // Replies only land on a post that still holds their parent.
+        let result = sqlx::query(
+            "INSERT INTO comments (id, post_id, user_id, parent_id, first_name, content, \
+             created_at, edited_at) \
+             SELECT ?, p.id, ?, ?, ?, ?, ?, ? FROM posts p WHERE p.id = ? AND (? IS NULL OR \
+             EXISTS (SELECT 1 FROM comments c WHERE c.post_id = p.id AND c.id = ?))",
+        )
+        .bind(&comment.id)
+        .bind(&comment.userId)
+        .bind(&comment.parentId)
+        .bind(&comment.firstName)
+        .bind(&comment.content)
+        .bind(comment.createdAt)
+        .bind(comment.editedAt.map(|date| date.to_chrono()))
+        .bind(post_id.to_hex())
+        .bind(&comment.parentId)
+        .bind(&comment.parentId)
+        .execute(&self.pool)
+        .await
+        .map_err(write_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_comment(
+        &self,
+        post_id: ObjectId,
+        comment_id: &str,
+        content: &str,
+        edited_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE comments SET content = ?, edited_at = ? WHERE post_id = ? AND id = ?",
+        )
+        .bind(content)
+        .bind(edited_at)
+        .bind(post_id.to_hex())
+        .bind(comment_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove_comment(&self, post_id: ObjectId, comment_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM comments WHERE post_id = ? AND id = ?")
+            .bind(post_id.to_hex())
+            .bind(comment_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_post(
+        &self,
+        post_id: ObjectId,
+        previous: &PostRevision,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<PostModel>> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE posts SET title = ?, content = ?, updated_at = ? \
+             WHERE id = ? AND title = ? AND content = ?",
+        )
+        .bind(title)
+        .bind(content)
+        .bind(previous.editedAt)
+        .bind(post_id.to_hex())
+        .bind(&previous.title)
+        .bind(&previous.content)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        sqlx::query(
+            "INSERT INTO post_revisions (post_id, title, content, edited_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(post_id.to_hex())
+        .bind(&previous.title)
+        .bind(&previous.content)
+        .bind(previous.editedAt)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        self.find_post(post_id).await
+    }
+
+    async fn rename_author(&self, user_id: &str, first_name: &str) -> Result<()> {
+        for statement in [
+            "UPDATE posts SET first_name = ? WHERE user_id = ?",
+            "UPDATE comments SET first_name = ? WHERE user_id = ?",
+        ] {
+            sqlx::query(statement)
+                .bind(first_name)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TokenStore for MySqlStore {
+    async fn insert_refresh_token(&self, token: &RefreshTokenModel) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, family, token_hash, used, revoked, created_at, \
+             expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(token.userId.to_hex())
+        .bind(&token.family)
+        .bind(&token.tokenHash)
+        .bind(token.used)
+        .bind(token.revoked)
+        .bind(token.createdAt)
+        .bind(token.expiresAt)
+        .execute(&self.pool)
+        .await
+        .map_err(write_error)?;
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET used = TRUE \
+             WHERE token_hash = ? AND used = FALSE AND revoked = FALSE",
+        )
+        .bind(token_hash)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        self.find_refresh_token(token_hash).await
+    }
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshTokenModel>> {
+        let row: Option<RefreshTokenRow> = sqlx::query_as(
+            "SELECT user_id, family, token_hash, used, revoked, created_at, expires_at \
+             FROM refresh_tokens WHERE token_hash = ?",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(RefreshTokenRow::into_model).transpose()?)
+    }
+
+    async fn revoke_refresh_family(&self, family: &str) -> Result<()> {
+        sqlx::query("UPDATE refresh_tokens SET revoked = TRUE WHERE family = ?")
+            .bind(family)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: ObjectId) -> Result<()> {
+        sqlx::query(
+            "UPDATE refresh_tokens SET revoked = TRUE WHERE user_id = ? AND revoked = FALSE",
+        )
+        .bind(user_id.to_hex())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn revoke_token(&self, token: &RevokedTokenModel) -> Result<()> {
+        sqlx::query("INSERT INTO revoked_tokens (jti, expires_at) VALUES (?, ?)")
+            .bind(&token.jti)
+            .bind(token.expiresAt)
+            .execute(&self.pool)
+            .await
+            .map_err(write_error)?;
+        Ok(())
+    }
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT jti FROM revoked_tokens WHERE jti = ?")
+                .bind(jti)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.is_some())
+    }
+
+    async fn delete_expired_tokens(&self, now: DateTime<Utc>) -> Result<()> {
+        // MySQL has no TTL indexes, so expired rows are deleted by hand.
+        sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM refresh_tokens WHERE expires_at < ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
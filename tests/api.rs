@@ -0,0 +1,674 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use http_body_util::BodyExt;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use kedubak::{
+    config::{JwtConfig, LimitsConfig},
+    db::{JwtKeys, DB},
+    model::Claims,
+    route::create_router,
+    store::memory::MemoryStore,
+    AppState,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+fn app() -> Router {
+    let jwt_keys = JwtKeys::parse("test:secret").unwrap();
+    let db = DB::new(
+        Arc::new(MemoryStore::new()),
+        JwtConfig::with_keys(jwt_keys),
+        LimitsConfig::default(),
+    );
+    create_router(Arc::new(AppState { db }))
+}
+
+async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, json)
+}
+
+/// Registers a user and returns its `(token, refreshToken)`.
+async fn register(app: &Router, email: &str, first_name: &str) -> (String, String) {
+    let (status, body) = send(
+        app,
+        Method::POST,
+        "/auth/register",
+        None,
+        Some(json!({
+            "email": email,
+            "password": "hunter2",
+            "firstName": first_name,
+            "lastName": "Doe",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    (
+        body["data"]["token"].as_str().unwrap().to_string(),
+        body["data"]["refreshToken"].as_str().unwrap().to_string(),
+    )
+}
+
+/// Creates a post and returns its id.
+async fn create_post(app: &Router, token: &str, title: &str, content: &str) -> String {
+    let (status, body) = send(
+        app,
+        Method::POST,
+        "/post",
+        Some(token),
+        Some(json!({"title": title, "content": content})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    body["data"]["_id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn register_and_login() {
+    let app = app();
+    register(&app, "jane@example.com", "Jane").await;
+
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/auth/register",
+        None,
+        Some(json!({
+            "email": "jane@example.com",
+            "password": "other",
+            "firstName": "Jane",
+            "lastName": "Doe",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        body,
+        json!({
+            "ok": false,
+            "code": "conflict",
+            "message": "an account with this email already exists",
+        })
+    );
+
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/auth/login",
+        None,
+        Some(json!({"email": "jane@example.com", "password": "hunter2"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["data"]["user"]["firstName"], "Jane");
+    let token = body["data"]["token"].as_str().unwrap();
+
+    let (status, body) = send(&app, Method::GET, "/user/me", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["email"], "jane@example.com");
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/auth/login",
+        None,
+        Some(json!({"email": "jane@example.com", "password": "wrong"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn protected_routes_require_a_token() {
+    let app = app();
+
+    let (status, body) = send(&app, Method::GET, "/user/me", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["code"], "missing_token");
+
+    let (status, _) = send(&app, Method::GET, "/user/me", Some("not-a-jwt"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn post_lifecycle() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    let (john, _) = register(&app, "john@example.com", "John").await;
+    let id = create_post(&app, &jane, "Hello", "First post").await;
+
+    let (status, body) = send(&app, Method::GET, &format!("/post/{}", id), Some(&jane), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["title"], "Hello");
+    assert_eq!(body["data"]["firstName"], "Jane");
+
+    let (status, _) = send(&app, Method::GET, "/post/not-an-id", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/post/{}", id), Some(&john), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) =
+        send(&app, Method::DELETE, &format!("/post/{}", id), Some(&jane), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["removed"], true);
+
+    let (status, body) =
+        send(&app, Method::GET, &format!("/post/{}", id), Some(&jane), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["message"], format!("post {} not found", id));
+}
+
+#[tokio::test]
+async fn rejected_requests_use_the_error_envelope() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/post")
+        .header(header::AUTHORIZATION, format!("Bearer {}", jane))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"title\": "))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["code"], "bad_request");
+
+    let (status, body) =
+        send(&app, Method::POST, "/post", Some(&jane), Some(json!({"title": "No content"}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+
+    for uri in ["/post/search", "/post?page=abc"] {
+        let (status, body) = send(&app, Method::GET, uri, Some(&jane), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+        assert_eq!(body["ok"], false);
+        assert_eq!(body["code"], "validation_failed");
+    }
+
+    let id = create_post(&app, &jane, "Hello", "Body").await;
+    let uri = format!("/comment/{}", id);
+    let (status, body) =
+        send(&app, Method::POST, &uri, Some(&jane), Some(json!({"content": " "}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+}
+
+#[tokio::test]
+async fn invalid_fields_are_listed_in_details() {
+    let app = app();
+
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/auth/register",
+        None,
+        Some(json!({
+            "email": "not-an-email",
+            "password": "",
+            "firstName": "Jane",
+            "lastName": " ",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(
+        body["details"],
+        json!([
+            {"field": "lastName", "message": "must not be empty"},
+            {"field": "password", "message": "must not be empty"},
+            {"field": "email", "message": "is invalid"},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn edit_post_keeps_history() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    let (john, _) = register(&app, "john@example.com", "John").await;
+    let id = create_post(&app, &jane, "Hello", "First post").await;
+    let uri = format!("/post/{}", id);
+
+    let (status, _) =
+        send(&app, Method::PUT, &uri, Some(&john), Some(json!({"title": "Mine"}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, Method::PUT, &uri, Some(&jane), Some(json!({}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, body) =
+        send(&app, Method::PUT, &uri, Some(&jane), Some(json!({"title": "Hello again"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["title"], "Hello again");
+    assert_eq!(body["data"]["content"], "First post");
+    assert!(body["data"]["updatedAt"].is_string());
+
+    let (status, body) =
+        send(&app, Method::GET, &format!("/post/{}/history", id), Some(&jane), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["title"], "Hello");
+}
+
+#[tokio::test]
+async fn votes_are_unique_and_rate_limited() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    let (john, _) = register(&app, "john@example.com", "John").await;
+    let first = create_post(&app, &jane, "One", "First").await;
+    let second = create_post(&app, &jane, "Two", "Second").await;
+
+    let (status, _) =
+        send(&app, Method::POST, &format!("/post/vote/{}", first), Some(&john), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) =
+        send(&app, Method::POST, &format!("/post/vote/{}", first), Some(&john), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) =
+        send(&app, Method::POST, &format!("/post/vote/{}", second), Some(&john), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (_, body) = send(&app, Method::GET, &format!("/post/{}", first), Some(&jane), None).await;
+    assert_eq!(body["data"]["upVotes"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn comments_are_threaded() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    let (john, _) = register(&app, "john@example.com", "John").await;
+    let (jack, _) = register(&app, "jack@example.com", "Jack").await;
+    let id = create_post(&app, &jane, "Hello", "First post").await;
+    let uri = format!("/comment/{}", id);
+
+    let (status, body) =
+        send(&app, Method::POST, &uri, Some(&john), Some(json!({"content": "Nice"}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let parent = body["data"]["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        &uri,
+        Some(&jane),
+        Some(json!({"content": "Thanks", "parentId": parent})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        &uri,
+        Some(&jane),
+        Some(json!({"content": "Orphan", "parentId": "missing"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = send(&app, Method::GET, &format!("/post/{}", id), Some(&jane), None).await;
+    let comments = body["data"]["comments"].as_array().unwrap();
+    assert_eq!(comments.len(), 2);
+    assert_eq!(comments[0]["depth"], 0);
+    assert_eq!(comments[1]["depth"], 1);
+    assert_eq!(comments[1]["parentId"], parent.as_str());
+
+    let comment_uri = format!("/comment/{}/{}", id, parent);
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        &comment_uri,
+        Some(&jack),
+        Some(json!({"content": "Hijacked"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(
+        &app,
+        Method::PUT,
+        &comment_uri,
+        Some(&john),
+        Some(json!({"content": "Very nice"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["content"], "Very nice");
+    assert!(body["data"]["editedAt"].is_string());
+
+    // The post author may moderate comments on their post.
+    let (status, _) = send(&app, Method::DELETE, &comment_uri, Some(&jane), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&app, Method::DELETE, &comment_uri, Some(&jane), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn edit_user_renames_authored_content() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    let id = create_post(&app, &jane, "Hello", "First post").await;
+    send(
+        &app,
+        Method::POST,
+        &format!("/comment/{}", id),
+        Some(&jane),
+        Some(json!({"content": "Self reply"})),
+    )
+    .await;
+
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        "/user/edit",
+        Some(&jane),
+        Some(json!({"email": "new@example.com"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, body) = send(
+        &app,
+        Method::PUT,
+        "/user/edit",
+        Some(&jane),
+        Some(json!({"firstName": "Janet"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["firstName"], "Janet");
+    assert_eq!(body["data"]["email"], "jane@example.com");
+
+    let (_, body) = send(&app, Method::GET, &format!("/post/{}", id), Some(&jane), None).await;
+    assert_eq!(body["data"]["firstName"], "Janet");
+    assert_eq!(body["data"]["comments"][0]["firstName"], "Janet");
+}
+
+#[tokio::test]
+async fn refresh_tokens_rotate_and_detect_reuse() {
+    let app = app();
+    let (_, refresh) = register(&app, "jane@example.com", "Jane").await;
+
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/auth/refresh",
+        None,
+        Some(json!({"refreshToken": refresh})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let rotated = body["data"]["refreshToken"].as_str().unwrap().to_string();
+    assert_ne!(rotated, refresh);
+
+    // Replaying the first token revokes the whole family, including the
+    // token it was rotated into.
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/auth/refresh",
+        None,
+        Some(json!({"refreshToken": refresh})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/auth/refresh",
+        None,
+        Some(json!({"refreshToken": rotated})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logout_revokes_tokens() {
+    let app = app();
+    let (token, refresh) = register(&app, "jane@example.com", "Jane").await;
+
+    let (status, _) = send(&app, Method::POST, "/auth/logout", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&app, Method::GET, "/user/me", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/auth/refresh",
+        None,
+        Some(json!({"refreshToken": refresh})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn tokens_without_jti_are_rejected() {
+    let app = app();
+    let (token, _) = register(&app, "jane@example.com", "Jane").await;
+
+    // Re-sign the same claims without a jti.
+    let validation = Validation::new(Algorithm::HS384);
+    let key = DecodingKey::from_secret(b"secret");
+    let mut claims = decode::<Claims>(&token, &key, &validation).unwrap().claims;
+    claims.jti = String::new();
+    let mut header = Header::new(Algorithm::HS384);
+    header.kid = Some("test".to_string());
+    let forged = encode(&header, &claims, &EncodingKey::from_secret(b"secret")).unwrap();
+
+    let (status, body) = send(&app, Method::GET, "/user/me", Some(&forged), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "invalid_token");
+    let (status, _) = send(&app, Method::POST, "/auth/logout", Some(&forged), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logout_keeps_other_sessions() {
+    let app = app();
+    let (laptop, _) = register(&app, "jane@example.com", "Jane").await;
+    let (_, body) = send(
+        &app,
+        Method::POST,
+        "/auth/login",
+        None,
+        Some(json!({"email": "jane@example.com", "password": "hunter2"})),
+    )
+    .await;
+    let phone = body["data"]["token"].as_str().unwrap().to_string();
+    let phone_refresh = body["data"]["refreshToken"].as_str().unwrap().to_string();
+
+    let (status, _) = send(&app, Method::POST, "/auth/logout", Some(&laptop), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&app, Method::GET, "/user/me", Some(&phone), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/auth/refresh",
+        None,
+        Some(json!({"refreshToken": phone_refresh})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // A token obtained by refreshing still belongs to the phone session.
+    let phone = body["data"]["token"].as_str().unwrap().to_string();
+    let phone_refresh = body["data"]["refreshToken"].as_str().unwrap().to_string();
+    let (status, _) = send(&app, Method::POST, "/auth/logout", Some(&phone), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/auth/refresh",
+        None,
+        Some(json!({"refreshToken": phone_refresh})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logout_all_revokes_every_session() {
+    let app = app();
+    let (first, _) = register(&app, "jane@example.com", "Jane").await;
+    let (_, body) = send(
+        &app,
+        Method::POST,
+        "/auth/login",
+        None,
+        Some(json!({"email": "jane@example.com", "password": "hunter2"})),
+    )
+    .await;
+    let second = body["data"]["token"].as_str().unwrap().to_string();
+
+    let (status, _) = send(&app, Method::POST, "/auth/logout/all", Some(&first), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&app, Method::GET, "/user/me", Some(&second), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn remove_user_cascades() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    let (john, _) = register(&app, "john@example.com", "John").await;
+    create_post(&app, &jane, "Mine", "Gone with me").await;
+    let other = create_post(&app, &john, "Theirs", "Stays").await;
+    send(
+        &app,
+        Method::POST,
+        &format!("/comment/{}", other),
+        Some(&jane),
+        Some(json!({"content": "Hi"})),
+    )
+    .await;
+    send(&app, Method::POST, &format!("/post/vote/{}", other), Some(&jane), None).await;
+
+    let (status, body) = send(&app, Method::DELETE, "/user/remove", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["postsRemoved"], 1);
+    assert_eq!(body["data"]["commentsRemoved"], 1);
+    assert_eq!(body["data"]["votesRemoved"], 1);
+
+    let (_, body) = send(&app, Method::GET, &format!("/post/{}", other), Some(&john), None).await;
+    assert_eq!(body["data"]["comments"].as_array().unwrap().len(), 0);
+    assert_eq!(body["data"]["upVotes"].as_array().unwrap().len(), 0);
+
+    let (status, _) = send(&app, Method::GET, "/user/me", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn listings_paginate() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    let (john, _) = register(&app, "john@example.com", "John").await;
+    for i in 0..5 {
+        create_post(&app, &jane, &format!("Post {}", i), "Body").await;
+    }
+    create_post(&app, &john, "Other", "Body").await;
+
+    let (status, body) = send(&app, Method::GET, "/post?page=2&limit=4", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 6);
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    let (status, _) = send(&app, Method::GET, "/post?page=0", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let uri = format!("/post?page={}&limit=100", usize::MAX);
+    let (status, body) = send(&app, Method::GET, &uri, Some(&jane), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["message"], "page is out of range");
+
+    let (_, body) = send(&app, Method::GET, "/post/me", Some(&jane), None).await;
+    assert_eq!(body["total"], 5);
+
+    // Walking the cursor visits every post exactly once, newest first.
+    let mut seen = Vec::new();
+    let mut cursor = String::new();
+    loop {
+        let uri = format!("/post?limit=2&cursor={}", cursor);
+        let (status, body) = send(&app, Method::GET, &uri, Some(&jane), None).await;
+        assert_eq!(status, StatusCode::OK);
+        for post in body["data"].as_array().unwrap() {
+            seen.push(post["title"].as_str().unwrap().to_string());
+        }
+        match body["next_cursor"].as_str() {
+            Some(next) => cursor = next.to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(seen, ["Other", "Post 4", "Post 3", "Post 2", "Post 1", "Post 0"]);
+
+    let (status, _) = send(&app, Method::GET, "/post?cursor=bogus", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn search_finds_matching_posts() {
+    let app = app();
+    let (jane, _) = register(&app, "jane@example.com", "Jane").await;
+    create_post(&app, &jane, "Rust tips", "Borrowing in rust").await;
+    create_post(&app, &jane, "Cooking", "Pasta with rust-free pans").await;
+    create_post(&app, &jane, "Gardening", "Tomatoes").await;
+
+    let (status, body) = send(&app, Method::GET, "/post/search?q=rust", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["data"][0]["title"], "Rust tips");
+    assert!(body["data"][0]["score"].is_number());
+
+    let uri = format!("/post/search?q=rust&page={}", usize::MAX);
+    let (status, _) = send(&app, Method::GET, &uri, Some(&jane), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = send(&app, Method::GET, "/post/search?q=", Some(&jane), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
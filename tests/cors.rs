@@ -0,0 +1,94 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderName, Method, Request, Response},
+    routing::get,
+    Router,
+};
+use kedubak::{config::CorsConfig, cors};
+use tower::ServiceExt;
+
+fn config(origins: &[&str]) -> CorsConfig {
+    CorsConfig {
+        origins: origins.iter().map(|origin| origin.parse().unwrap()).collect(),
+        exposed_headers: vec![HeaderName::from_static("x-total-count")],
+        max_age_secs: Some(600),
+    }
+}
+
+async fn send(config: &CorsConfig, method: Method, origin: &str) -> Response<Body> {
+    let app = Router::new()
+        .route("/", get(|| async { "ok" }))
+        .layer(cors::layer(config));
+    let mut request = Request::builder()
+        .method(method.clone())
+        .uri("/")
+        .header(header::ORIGIN, origin);
+    if method == Method::OPTIONS {
+        request = request.header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET");
+    }
+    app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+fn allowed_origin(response: &Response<Body>) -> Option<&str> {
+    response
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn allows_each_listed_origin() {
+    let config = config(&["http://localhost:3000", "https://staging.example.com"]);
+
+    for origin in ["http://localhost:3000", "https://staging.example.com"] {
+        let response = send(&config, Method::GET, origin).await;
+        assert_eq!(allowed_origin(&response), Some(origin));
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "x-total-count"
+        );
+    }
+    let response = send(&config, Method::GET, "https://evil.example.org").await;
+    assert_eq!(allowed_origin(&response), None);
+}
+
+#[tokio::test]
+async fn wildcard_matches_subdomains_only() {
+    let config = config(&["https://*.example.com"]);
+
+    for origin in ["https://app.example.com", "https://eu.app.example.com"] {
+        let response = send(&config, Method::GET, origin).await;
+        assert_eq!(allowed_origin(&response), Some(origin));
+    }
+    for origin in [
+        "https://example.com",
+        "http://app.example.com",
+        "https://app.example.com.evil.org",
+        "https://evilexample.com",
+    ] {
+        let response = send(&config, Method::GET, origin).await;
+        assert_eq!(allowed_origin(&response), None, "{}", origin);
+    }
+}
+
+#[tokio::test]
+async fn preflight_sends_max_age() {
+    let config = config(&["https://*.example.com"]);
+
+    let response = send(&config, Method::OPTIONS, "https://app.example.com").await;
+    assert_eq!(allowed_origin(&response), Some("https://app.example.com"));
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_MAX_AGE], "600");
+}
+
+#[test]
+fn rejects_malformed_patterns() {
+    for pattern in [
+        "localhost:3000",
+        "https://app.*.example.com",
+        "https://*example.com",
+        "https://example.com/app",
+        "*",
+    ] {
+        assert!(pattern.parse::<cors::OriginPattern>().is_err(), "{}", pattern);
+    }
+}
@@ -0,0 +1,185 @@
+//! Behaviour every storage backend must share. The MySQL run needs a
+//! disposable database: `MYSQL_TEST_URL=mysql://... cargo test -- --ignored`.
+
+use chrono::{Duration, SubsecRound, Utc};
+use kedubak::{
+    config::{BackendConfig, DatabaseConfig},
+    error::MyError,
+    model::{Comments, PostModel, RefreshTokenModel, RevokedTokenModel, UserModel},
+    store::{memory::MemoryStore, mysql::MySqlStore, PostQuery, Store},
+};
+use mongodb::bson::oid::ObjectId;
+use uuid::Uuid;
+
+fn user(email: &str) -> UserModel {
+    let now = Utc::now();
+    UserModel {
+        id: ObjectId::new(),
+        email: email.to_string(),
+        firstName: "Jane".to_string(),
+        lastName: "Doe".to_string(),
+        password: "hash".to_string(),
+        createdAt: now,
+        lastUpVote: now - Duration::minutes(5),
+        tokenGeneration: 0,
+    }
+}
+
+fn post(author: &UserModel, title: &str) -> PostModel {
+    PostModel {
+        id: Some(ObjectId::new()),
+        userId: author.id.to_string(),
+        title: title.to_string(),
+        content: "Body".to_string(),
+        firstName: author.firstName.to_string(),
+        comments: vec![],
+        upVotes: vec![],
+        createdAt: Utc::now(),
+        updatedAt: None,
+        history: vec![],
+    }
+}
+
+fn comment(author: &UserModel, parent: Option<&str>) -> Comments {
+    Comments {
+        id: Uuid::new_v4().to_string(),
+        userId: author.id.to_string(),
+        parentId: parent.map(str::to_string),
+        firstName: author.firstName.to_string(),
+        content: "Hi".to_string(),
+        createdAt: Utc::now(),
+        editedAt: None,
+    }
+}
+
+async fn contract(store: &dyn Store) {
+    // Runs may share a database, so every email is unique to this run.
+    let run = Uuid::new_v4().simple().to_string();
+    let jane = user(&format!("jane-{}@example.com", run));
+    let john = user(&format!("john-{}@example.com", run));
+    store.insert_user(&jane).await.unwrap();
+    store.insert_user(&john).await.unwrap();
+
+    let found = store.find_user_by_email(&jane.email).await.unwrap().unwrap();
+    assert_eq!(found.id, jane.id);
+    assert!(matches!(
+        store.insert_user(&user(&jane.email)).await,
+        Err(MyError::DuplicateError(_) | MyError::MongoDuplicateError(_))
+    ));
+    // Emails are compared exactly, as MongoDB does.
+    let shouting = user(&jane.email.to_uppercase());
+    store.insert_user(&shouting).await.unwrap();
+    assert!(store.find_user_by_email(&jane.email.to_uppercase()).await.unwrap().is_some());
+
+    let now = Utc::now().trunc_subsecs(3);
+    let cooldown_end = now - Duration::minutes(1);
+    assert!(store.claim_upvote(jane.id, cooldown_end, now).await.unwrap());
+    assert!(!store.claim_upvote(jane.id, cooldown_end, now).await.unwrap());
+    // Releasing someone else's claim is a no-op, releasing ours frees the slot.
+    let later = now + Duration::seconds(1);
+    store.release_upvote(jane.id, later, cooldown_end).await.unwrap();
+    assert!(!store.claim_upvote(jane.id, cooldown_end, now).await.unwrap());
+    store.release_upvote(jane.id, now, cooldown_end).await.unwrap();
+    assert!(store.claim_upvote(jane.id, cooldown_end, now).await.unwrap());
+
+    // Titles have no length limit.
+    let long_title = "t".repeat(1000);
+    let first = post(&jane, &long_title);
+    let first_id = first.id.unwrap();
+    store.insert_post(&first).await.unwrap();
+    let second = post(&jane, "Second");
+    store.insert_post(&second).await.unwrap();
+    let theirs = post(&john, "Theirs");
+    let theirs_id = theirs.id.unwrap();
+    store.insert_post(&theirs).await.unwrap();
+
+    let author = jane.id.to_string();
+    assert_eq!(store.count_posts(Some(&author)).await.unwrap(), 2);
+    let page = store
+        .list_posts(&PostQuery {
+            author: Some(author.clone()),
+            after: None,
+            skip: 0,
+            limit: 10,
+        })
+        .await
+        .unwrap();
+    let titles: Vec<&str> = page.iter().map(|post| post.title.as_str()).collect();
+    assert_eq!(titles, vec!["Second", long_title.as_str()]);
+
+    assert!(store.add_vote(first_id, &john.id.to_string()).await.unwrap());
+    assert!(!store.add_vote(first_id, &john.id.to_string()).await.unwrap());
+    assert!(!store.add_vote(ObjectId::new(), &john.id.to_string()).await.unwrap());
+
+    let top = comment(&john, None);
+    assert!(store.push_comment(first_id, &top).await.unwrap());
+    assert!(store.push_comment(first_id, &comment(&jane, Some(&top.id))).await.unwrap());
+    assert!(!store.push_comment(first_id, &comment(&jane, Some("missing"))).await.unwrap());
+    assert!(store.update_comment(first_id, &top.id, "Edited", Utc::now()).await.unwrap());
+    let stored = store.find_post(first_id).await.unwrap().unwrap();
+    assert_eq!(stored.title, long_title);
+    assert_eq!(stored.upVotes, vec![john.id.to_string()]);
+    assert_eq!(stored.comments.len(), 2);
+    assert_eq!(stored.comments[0].content, "Edited");
+
+    assert!(store.push_comment(theirs_id, &comment(&jane, None)).await.unwrap());
+    assert!(store.add_vote(theirs_id, &jane.id.to_string()).await.unwrap());
+
+    let refresh = RefreshTokenModel {
+        id: None,
+        userId: jane.id,
+        family: Uuid::new_v4().to_string(),
+        tokenHash: format!("hash-{}", run),
+        used: false,
+        revoked: false,
+        createdAt: Utc::now(),
+        expiresAt: Utc::now() + Duration::days(1),
+    };
+    store.insert_refresh_token(&refresh).await.unwrap();
+    assert!(store.consume_refresh_token(&refresh.tokenHash).await.unwrap().is_some());
+    assert!(store.consume_refresh_token(&refresh.tokenHash).await.unwrap().is_none());
+
+    let jti = format!("jti-{}", run);
+    assert!(!store.is_token_revoked(&jti).await.unwrap());
+    let revoked = RevokedTokenModel {
+        jti: jti.clone(),
+        expiresAt: Utc::now() + Duration::hours(1),
+    };
+    store.revoke_token(&revoked).await.unwrap();
+    assert!(store.is_token_revoked(&jti).await.unwrap());
+
+    // Sweeping expired tokens leaves live ones alone.
+    store.delete_expired_tokens(Utc::now()).await.unwrap();
+    assert!(store.is_token_revoked(&jti).await.unwrap());
+    assert!(store.find_refresh_token(&refresh.tokenHash).await.unwrap().is_some());
+    store.delete_expired_tokens(Utc::now() + Duration::days(2)).await.unwrap();
+    assert!(!store.is_token_revoked(&jti).await.unwrap());
+    assert!(store.find_refresh_token(&refresh.tokenHash).await.unwrap().is_none());
+
+    let counts = store.delete_user_cascade(jane.id).await.unwrap().unwrap();
+    assert_eq!((counts.posts, counts.comments, counts.votes), (2, 1, 1));
+    assert!(store.find_post(first_id).await.unwrap().is_none());
+    let theirs = store.find_post(theirs_id).await.unwrap().unwrap();
+    assert!(theirs.comments.is_empty());
+    assert!(theirs.upVotes.is_empty());
+    assert!(store.delete_user_cascade(jane.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn memory_store_contract() {
+    contract(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a MySQL database in MYSQL_TEST_URL"]
+async fn mysql_store_contract() {
+    let url = std::env::var("MYSQL_TEST_URL").expect("MYSQL_TEST_URL must be set");
+    let store = MySqlStore::init(&DatabaseConfig {
+        backend: BackendConfig::MySql,
+        url,
+        max_connections: 5,
+    })
+    .await
+    .unwrap();
+    contract(&store).await;
+}